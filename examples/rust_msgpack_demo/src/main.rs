@@ -38,7 +38,7 @@ fn main() {
     println!("bin = {:?}\n", bin);
 
     let v2 = decode::decode_to_value(&bin).unwrap();
-    println!("v2 = {}", v2);
+    println!("v2 = {:#}", v2);
 
     let s2: Student = v2.from_value();
     println!("s2 = {:?}", s2);
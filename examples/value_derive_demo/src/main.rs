@@ -34,7 +34,7 @@ fn main() {
     println!("s1 = {:?}\n", s1);
 
     let v: Value = s1.into_value();
-    println!("v = {}", v);
+    println!("v = {:#}", v);
 
     let s2: Student = v.from_value();
     println!("s2 = {:?}", s2);
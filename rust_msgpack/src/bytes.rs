@@ -8,7 +8,7 @@ pub struct Reader<'a> {
 
 impl<'a> Reader<'a> {
     pub fn new(s: &'a [u8]) -> Self {
-        Self { s: s, i: 0 }
+        Self { s, i: 0 }
     }
 
     // len returns the number of bytes of the unread portion of the slice.
@@ -19,17 +19,21 @@ impl<'a> Reader<'a> {
         (self.s.len() as i64) - self.i
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn size(&self) -> i64 {
         self.s.len() as i64
     }
 
-    pub fn read(&mut self, mut b: &mut [u8]) -> Result<i64, RMError> {
+    pub fn read(&mut self, b: &mut [u8]) -> Result<i64, RMError> {
         if self.i >= (self.s.len() as i64) {
             return Err(RMError::EOFError);
         }
 
         let start_idx = self.i as usize;
-        let n = utils::slice_copy(&mut b, &self.s[start_idx..]);
+        let n = utils::slice_copy(b, &self.s[start_idx..]);
         if n == 0 {
             return Err(RMError::EOFError);
         }
@@ -38,7 +42,7 @@ impl<'a> Reader<'a> {
         Ok(n)
     }
 
-    pub fn read_at(&mut self, mut b: &mut [u8], off: i64) -> Result<i64, RMError> {
+    pub fn read_at(&mut self, b: &mut [u8], off: i64) -> Result<i64, RMError> {
         if off < 0 {
             return Err(RMError::EOFError);
         }
@@ -48,7 +52,18 @@ impl<'a> Reader<'a> {
         }
 
         let start_idx = off as usize;
-        Ok(utils::slice_copy(&mut b, &self.s[start_idx..]))
+        Ok(utils::slice_copy(b, &self.s[start_idx..]))
+    }
+
+    // read_slice returns the next n bytes without copying them.
+    pub fn read_slice(&mut self, n: usize) -> Result<&'a [u8], RMError> {
+        if n as i64 > self.len() {
+            return Err(RMError::EOFError);
+        }
+
+        let start_idx = self.i as usize;
+        self.i += n as i64;
+        Ok(&self.s[start_idx..start_idx + n])
     }
 
     pub fn read_byte(&mut self) -> Result<u8, RMError> {
@@ -82,7 +97,7 @@ mod tests {
     fn test_bytes_reader() {
         let a = String::from("hello");
         let b = a.as_bytes();
-        let mut r = Reader::new(&b);
+        let mut r = Reader::new(b);
         assert_eq!(r.len(), 5);
         assert_eq!(r.size(), 5);
 
@@ -117,36 +132,42 @@ mod tests {
         r.reset();
         let mut dst = vec![b'0'; 7];
         r.read(&mut dst).unwrap();
-        assert_eq!(&dst, &[b'h', b'e', b'l', b'l', b'o', b'0', b'0']);
+        assert_eq!(&dst, b"hello00");
 
         r.reset();
         let mut dst = vec![b'0'; 2];
         r.read(&mut dst).unwrap();
-        assert_eq!(&dst, &[b'h', b'e']);
+        assert_eq!(&dst, b"he");
 
         r.reset();
         let mut dst = vec![b'0'; 2];
         r.read_at(&mut dst, 0).unwrap();
-        assert_eq!(&dst, &[b'h', b'e']);
+        assert_eq!(&dst, b"he");
 
         r.reset();
         let mut dst = vec![b'0'; 2];
         r.read_at(&mut dst, 1).unwrap();
-        assert_eq!(&dst, &[b'e', b'l']);
+        assert_eq!(&dst, b"el");
 
         r.reset();
         let mut dst = vec![b'0'; 2];
         r.read_at(&mut dst, 2).unwrap();
-        assert_eq!(&dst, &[b'l', b'l']);
+        assert_eq!(&dst, b"ll");
 
         r.reset();
         let mut dst = vec![b'0'; 2];
         r.read_at(&mut dst, 3).unwrap();
-        assert_eq!(&dst, &[b'l', b'o']);
+        assert_eq!(&dst, b"lo");
 
         r.reset();
         let mut dst = vec![b'0'; 2];
         r.read_at(&mut dst, 4).unwrap();
-        assert_eq!(&dst, &[b'o', b'0']);
+        assert_eq!(&dst, b"o0");
+
+        r.reset();
+        assert_eq!(r.read_slice(2).unwrap(), b"he");
+        assert_eq!(r.read_slice(3).unwrap(), b"llo");
+        assert_eq!(r.read_slice(0).unwrap(), b"");
+        assert!(r.read_slice(1).is_err());
     }
 }
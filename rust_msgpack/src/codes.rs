@@ -227,19 +227,19 @@ pub fn is_fixed_num(c: Code) -> bool {
 }
 
 pub fn is_fixed_map(c: Code) -> bool {
-    c >= FIXED_MAP_LOW && c <= FIXED_MAP_HIGH
+    (FIXED_MAP_LOW..=FIXED_MAP_HIGH).contains(&c)
 }
 
 pub fn is_fixed_array(c: Code) -> bool {
-    c >= FIXED_ARRAY_LOW && c <= FIXED_ARRAY_HIGH
+    (FIXED_ARRAY_LOW..=FIXED_ARRAY_HIGH).contains(&c)
 }
 
 pub fn is_fixed_string(c: Code) -> bool {
-    c >= FIXED_STR_LOW && c <= FIXED_STR_HIGH
+    (FIXED_STR_LOW..=FIXED_STR_HIGH).contains(&c)
 }
 
 pub fn is_ext(c: Code) -> bool {
-    (c >= FIX_EXT_1 && c <= FIX_EXT_16) || (c >= EXT_8 && c <= EXT_32)
+    (FIX_EXT_1..=FIX_EXT_16).contains(&c) || (EXT_8..=EXT_32).contains(&c)
 }
 
 pub fn is_nil(c: Code) -> bool {
@@ -278,14 +278,14 @@ pub fn is_bin_array(c: Code) -> bool {
 }
 
 pub fn is_array(c: Code) -> bool {
-    if c >= FIXED_ARRAY_LOW && c <= FIXED_ARRAY_HIGH {
+    if (FIXED_ARRAY_LOW..=FIXED_ARRAY_HIGH).contains(&c) {
         return true;
     }
     c == ARRAY_16 || c == ARRAY_32
 }
 
 pub fn is_hashmap(c: Code) -> bool {
-    if c >= FIXED_MAP_LOW && c <= FIXED_MAP_HIGH {
+    if (FIXED_MAP_LOW..=FIXED_MAP_HIGH).contains(&c) {
         return true;
     }
     c == MAP_16 || c == MAP_32
@@ -2,18 +2,20 @@ use crate::binary;
 use crate::bytes;
 use crate::codes;
 use crate::error::Error as RMError;
+use crate::time;
 use crate::utils;
-use std::collections::HashMap;
-use std::time::Duration;
+use std::collections::HashSet;
 use std::time::SystemTime;
+use value::object_map::ObjectMap;
 use value::value::Value;
+use value::value_ref::ValueRef;
 
 pub struct Decoder<'a> {
     r: bytes::Reader<'a>,
 }
 
 impl<'a> Decoder<'a> {
-    pub fn new(s: &'a [u8]) -> Decoder {
+    pub fn new(s: &'a [u8]) -> Decoder<'a> {
         Decoder {
             r: bytes::Reader::new(s),
         }
@@ -30,12 +32,15 @@ impl<'a> Decoder<'a> {
     }
 
     pub fn read_n(&mut self, n: i32) -> Result<Vec<u8>, RMError> {
-        let mut buf: Vec<u8> = vec![0; n as usize];
-        let readcount = self.r.read(&mut buf)?;
-        if readcount != (n as i64) {
+        Ok(self.read_slice(n)?.to_vec())
+    }
+
+    // read_slice is read_n without the copy: the bytes borrow from the input.
+    pub fn read_slice(&mut self, n: i32) -> Result<&'a [u8], RMError> {
+        if n < 0 {
             return Err(RMError::RWNotMatch);
         }
-        Ok(buf)
+        self.r.read_slice(n as usize)
     }
 
     fn read_uint8(&mut self) -> Result<u8, RMError> {
@@ -87,35 +92,33 @@ impl<'a> Decoder<'a> {
         match c {
             codes::UINT_8 => {
                 let n = self.read_uint8()?;
-                return Ok(n as u64);
+                Ok(n as u64)
             }
             codes::INT_8 => {
                 let n = self.read_int8()?;
-                return Ok(n as u64);
+                Ok(n as u64)
             }
             codes::UINT_16 => {
                 let n = self.read_uint16()?;
-                return Ok(n as u64);
+                Ok(n as u64)
             }
             codes::INT_16 => {
                 let n = self.read_int16()?;
-                return Ok(n as u64);
+                Ok(n as u64)
             }
             codes::UINT_32 => {
                 let n = self.read_uint32()?;
-                return Ok(n as u64);
+                Ok(n as u64)
             }
             codes::INT_32 => {
                 let n = self.read_int32()?;
-                return Ok(n as u64);
+                Ok(n as u64)
             }
             codes::UINT_64 | codes::INT_64 => {
                 let n = self.read_uint64()?;
-                return Ok(n);
-            }
-            _ => {
-                return Err(RMError::InvalidCode(c));
+                Ok(n)
             }
+            _ => Err(RMError::InvalidCode(c)),
         }
     }
 
@@ -129,42 +132,51 @@ impl<'a> Decoder<'a> {
         match c {
             codes::UINT_8 => {
                 let n = self.read_uint8()?;
-                return Ok(n as i64);
+                Ok(n as i64)
             }
             codes::INT_8 => {
                 let n = self.read_uint8()?;
-                return Ok((n as i8) as i64);
+                Ok((n as i8) as i64)
             }
             codes::UINT_16 => {
                 let n = self.read_uint16()?;
-                return Ok(n as i64);
+                Ok(n as i64)
             }
             codes::INT_16 => {
                 let n = self.read_uint16()?;
-                return Ok((n as i16) as i64);
+                Ok((n as i16) as i64)
             }
             codes::UINT_32 => {
                 let n = self.read_uint32()?;
-                return Ok(n as i64);
+                Ok(n as i64)
             }
             codes::INT_32 => {
                 let n = self.read_uint32()?;
-                return Ok((n as i32) as i64);
+                Ok((n as i32) as i64)
             }
             codes::UINT_64 => {
                 let n = self.read_uint64()?;
-                return Ok(n as i64);
+                Ok(n as i64)
             }
             codes::INT_64 => {
                 let n = self.read_int64()?;
-                return Ok(n);
-            }
-            _ => {
-                return Err(RMError::InvalidCode(c));
+                Ok(n)
             }
+            _ => Err(RMError::InvalidCode(c)),
         }
     }
 
+    // read_len32 reads a 32-bit length prefix. Lengths running past the end
+    // of the input are rejected here, before they can wrap to a negative
+    // i32 (which would read as nil) or size an allocation.
+    fn read_len32(&mut self) -> Result<i32, RMError> {
+        let n = self.read_uint32()?;
+        if n > i32::MAX as u32 || i64::from(n) > self.r.len() {
+            return Err(RMError::EOFError);
+        }
+        Ok(n as i32)
+    }
+
     fn bytes_len(&mut self, c: codes::Code) -> Result<i32, RMError> {
         if c == codes::NIL {
             return Ok(-1);
@@ -177,8 +189,7 @@ impl<'a> Decoder<'a> {
             let n = self.read_uint16()?;
             return Ok(n as i32);
         } else if c == codes::STR_32 || c == codes::BIN_32 {
-            let n = self.read_uint32()?;
-            return Ok(n as i32);
+            return self.read_len32();
         }
         Err(RMError::InvalidCode(c))
     }
@@ -259,11 +270,40 @@ impl<'a> Decoder<'a> {
 
     pub fn decode_bytes_content(&mut self, c: codes::Code) -> Result<Vec<u8>, RMError> {
         let n = self.bytes_len(c)?;
-        if n == -1 {
+        if c == codes::NIL || n == 0 {
             return Ok(Vec::new());
         }
-        let b = self.read_n(n)?;
-        Ok(b)
+        self.read_n(n)
+    }
+}
+
+impl<'a> Decoder<'a> {
+    pub fn decode_str_ref(&mut self) -> Result<&'a str, RMError> {
+        let c = self.read_code()?;
+        self.decode_str_ref_content(c)
+    }
+
+    pub fn decode_str_ref_content(&mut self, c: codes::Code) -> Result<&'a str, RMError> {
+        let n = self.bytes_len(c)?;
+        if n <= 0 {
+            return Ok("");
+        }
+        let b = self.read_slice(n)?;
+        let s = std::str::from_utf8(b)?;
+        Ok(s)
+    }
+
+    pub fn decode_bytes_ref(&mut self) -> Result<&'a [u8], RMError> {
+        let c = self.read_code()?;
+        self.decode_bytes_ref_content(c)
+    }
+
+    pub fn decode_bytes_ref_content(&mut self, c: codes::Code) -> Result<&'a [u8], RMError> {
+        let n = self.bytes_len(c)?;
+        if c == codes::NIL {
+            return Ok(&[]);
+        }
+        self.read_slice(n)
     }
 }
 
@@ -320,31 +360,28 @@ impl<'a> Decoder<'a> {
 
 impl<'a> Decoder<'a> {
     pub fn decode_time(&mut self) -> Result<SystemTime, RMError> {
+        let (_, b) = self.decode_ext()?;
+        let (secs, nanos) = time::decode_timestamp(&b)?;
+        Ok(value::time::join_time(secs, nanos))
+    }
+
+    pub fn decode_ext(&mut self) -> Result<(i8, Vec<u8>), RMError> {
         let c = self.read_code()?;
-        let extlen = self.parse_ext_len(c)?;
+        self.read_ext(c)
+    }
 
-        // Skip ext id
-        self.read_byte()?;
+    pub fn read_ext(&mut self, c: codes::Code) -> Result<(i8, Vec<u8>), RMError> {
+        let extlen = self.parse_ext_len(c)?;
+        let type_id = self.read_int8()?;
+        let data = self.read_slice(extlen)?.to_vec();
+        Ok((type_id, data))
+    }
 
-        let b = self.read_n(extlen)?;
-        match b.len() {
-            4 => {
-                let sec = binary::BigEndian::uint32(&b);
-                Ok(SystemTime::UNIX_EPOCH + Duration::new(sec as u64, 0))
-            }
-            8 => {
-                let sec = binary::BigEndian::uint64(&b);
-                let nanos = (sec >> 32) as u32;
-                let secs = sec & 0x00000003ffffffff;
-                Ok(SystemTime::UNIX_EPOCH + Duration::new(secs, nanos))
-            }
-            12 => {
-                let nanos = binary::BigEndian::uint32(&b);
-                let secs = binary::BigEndian::uint64(&b[4..]);
-                Ok(SystemTime::UNIX_EPOCH + Duration::new(secs, nanos))
-            }
-            _ => Err(RMError::InvalidExtLen(b.len() as i32)),
-        }
+    pub fn read_ext_ref(&mut self, c: codes::Code) -> Result<(i8, &'a [u8]), RMError> {
+        let extlen = self.parse_ext_len(c)?;
+        let type_id = self.read_int8()?;
+        let data = self.read_slice(extlen)?;
+        Ok((type_id, data))
     }
 
     fn parse_ext_len(&mut self, c: codes::Code) -> Result<i32, RMError> {
@@ -356,16 +393,13 @@ impl<'a> Decoder<'a> {
             codes::FIX_EXT_16 => Ok(16),
             codes::EXT_8 => {
                 let n = self.read_uint8()?;
-                return Ok(n as i32);
+                Ok(n as i32)
             }
             codes::EXT_16 => {
                 let n = self.read_uint16()?;
-                return Ok(n as i32);
-            }
-            codes::EXT_32 => {
-                let n = self.read_uint32()?;
-                return Ok(n as i32);
+                Ok(n as i32)
             }
+            codes::EXT_32 => self.read_len32(),
             _ => Err(RMError::InvalidCode(c)),
         }
     }
@@ -387,12 +421,11 @@ impl<'a> Decoder<'a> {
         match c {
             codes::ARRAY_16 => {
                 let n = self.read_uint16()?;
-                return Ok(n as i32);
-            }
-            codes::ARRAY_32 => {
-                let n = self.read_uint32()?;
-                return Ok(n as i32);
+                Ok(n as i32)
             }
+            // Every element takes at least one byte, so the input bounds
+            // the count as well.
+            codes::ARRAY_32 => self.read_len32(),
             _ => Err(RMError::InvalidCode(c)),
         }
     }
@@ -414,23 +447,46 @@ impl<'a> Decoder<'a> {
         match c {
             codes::MAP_16 => {
                 let n = self.read_uint16()?;
-                return Ok(n as i32);
-            }
-            codes::MAP_32 => {
-                let n = self.read_uint32()?;
-                return Ok(n as i32);
+                Ok(n as i32)
             }
+            // Every element takes at least one byte, so the input bounds
+            // the count as well.
+            codes::MAP_32 => self.read_len32(),
             _ => Err(RMError::InvalidCode(c)),
         }
     }
 }
 
+// is_object_keys reports whether a msgpack map with these keys decodes as
+// an object: every key must be a string (None marks any other key) and no
+// key may repeat. Other maps stay maps, so no entry is dropped.
+pub(crate) fn is_object_keys<'k, I>(keys: I) -> bool
+where
+    I: Iterator<Item = Option<&'k str>>,
+{
+    let mut seen = HashSet::new();
+    for k in keys {
+        match k {
+            Some(k) if seen.insert(k) => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+// decode_to_value decodes one msgpack value.
+//
+// msgpack has a single map type, so maps are normalised: a map whose keys
+// are all distinct strings, including the empty map, becomes
+// Value::Object, and any other map becomes Value::Map. A Value::Map that
+// meets the object rule therefore decodes back as an Object with the same
+// entries.
 pub fn decode_to_value(v: &[u8]) -> Result<Value, RMError> {
-    let mut dec = Decoder::new(&v);
+    let mut dec = Decoder::new(v);
     decode_to_value_inner(&mut dec)
 }
 
-fn decode_to_value_inner(mut dec: &mut Decoder) -> Result<Value, RMError> {
+fn decode_to_value_inner(dec: &mut Decoder) -> Result<Value, RMError> {
     let c = dec.read_code()?;
     let result = match c {
         codes::NIL => Value::Null,
@@ -440,33 +496,32 @@ fn decode_to_value_inner(mut dec: &mut Decoder) -> Result<Value, RMError> {
         }
         codes::FLOAT_32 => {
             let result = dec.read_float32(c)?;
-            Value::Number(result.to_string())
+            Value::F32(result)
         }
         codes::FLOAT_64 => {
             let result = dec.read_float64(c)?;
-            Value::Number(result.to_string())
+            Value::F64(result)
         }
         codes::UINT_8 | codes::UINT_16 | codes::UINT_32 | codes::UINT_64 => {
             let result = dec.read_uint(c)?;
-            Value::Number(result.to_string())
+            Value::UInt(result)
         }
         codes::INT_8 | codes::INT_16 | codes::INT_32 | codes::INT_64 => {
             let result = dec.read_int(c)?;
-            Value::Number(result.to_string())
+            Value::Int(result)
         }
         codes::STR_8 | codes::STR_16 | codes::STR_32 => {
             let result = dec.decode_string_content(c)?;
             Value::String(result)
         }
         codes::BIN_8 | codes::BIN_16 | codes::BIN_32 => {
-            let bytes_array = dec.decode_bytes_content(c)?;
-            let result = unsafe { String::from_utf8_unchecked(bytes_array) };
-            Value::String(result)
+            let result = dec.decode_bytes_content(c)?;
+            Value::Binary(result)
         }
         _ => {
             if codes::is_fixed_num(c) {
                 let result = dec.read_int(c)?;
-                return Ok(Value::Number(result.to_string()));
+                return Ok(Value::Int(result));
             }
             if codes::is_fixed_string(c) {
                 let result = dec.decode_string_content(c)?;
@@ -477,7 +532,7 @@ fn decode_to_value_inner(mut dec: &mut Decoder) -> Result<Value, RMError> {
                 let mut result: Vec<Value> = Vec::new();
                 let mut i = 0;
                 while i < arraylen {
-                    let sub: Value = decode_to_value_inner(&mut dec)?;
+                    let sub: Value = decode_to_value_inner(dec)?;
                     result.push(sub);
                     i += 1;
                 }
@@ -485,22 +540,114 @@ fn decode_to_value_inner(mut dec: &mut Decoder) -> Result<Value, RMError> {
             }
             if codes::is_fixed_map(c) || c == codes::MAP_16 || c == codes::MAP_32 {
                 let maplen = dec.map_len(c)?;
-                let mut result: HashMap<String, Value> = HashMap::new();
+                let mut pairs: Vec<(Value, Value)> = Vec::new();
                 let mut i = 0;
                 while i < maplen {
-                    let mapkey: Value = decode_to_value_inner(&mut dec)?;
-                    let mapvalue: Value = decode_to_value_inner(&mut dec)?;
-                    result.insert(mapkey.get_string(), mapvalue);
+                    let mapkey: Value = decode_to_value_inner(dec)?;
+                    let mapvalue: Value = decode_to_value_inner(dec)?;
+                    pairs.push((mapkey, mapvalue));
                     i += 1;
                 }
+
+                if !is_object_keys(pairs.iter().map(|(k, _)| k.as_str())) {
+                    return Ok(Value::Map(pairs));
+                }
+                let mut result = ObjectMap::with_capacity(pairs.len());
+                for (k, v) in pairs {
+                    if let Value::String(k) = k {
+                        result.insert(k, v);
+                    }
+                }
                 return Ok(Value::Object(result));
             }
-            Value::default()
+            if codes::is_ext(c) {
+                let (type_id, data) = dec.read_ext(c)?;
+                if type_id == time::TIME_EXT_ID {
+                    let (secs, nanos) = time::decode_timestamp(&data)?;
+                    return Ok(Value::Timestamp { secs, nanos });
+                }
+                return Ok(Value::Ext { type_id, data });
+            }
+            return Err(RMError::InvalidCode(c));
         }
     };
     Ok(result)
 }
 
+// decode_to_value_ref decodes like decode_to_value, with the same map
+// normalisation, but strings, binaries and ext payloads borrow from v
+// instead of being copied.
+pub fn decode_to_value_ref(v: &[u8]) -> Result<ValueRef<'_>, RMError> {
+    let mut dec = Decoder::new(v);
+    decode_to_value_ref_inner(&mut dec)
+}
+
+fn decode_to_value_ref_inner<'a>(dec: &mut Decoder<'a>) -> Result<ValueRef<'a>, RMError> {
+    let c = dec.read_code()?;
+    let result = match c {
+        codes::NIL => ValueRef::Null,
+        codes::FALSE | codes::TRUE => ValueRef::Bool(dec.read_bool(c)?),
+        codes::FLOAT_32 => ValueRef::F32(dec.read_float32(c)?),
+        codes::FLOAT_64 => ValueRef::F64(dec.read_float64(c)?),
+        codes::UINT_8 | codes::UINT_16 | codes::UINT_32 | codes::UINT_64 => {
+            ValueRef::UInt(dec.read_uint(c)?)
+        }
+        codes::INT_8 | codes::INT_16 | codes::INT_32 | codes::INT_64 => {
+            ValueRef::Int(dec.read_int(c)?)
+        }
+        codes::STR_8 | codes::STR_16 | codes::STR_32 => {
+            ValueRef::String(dec.decode_str_ref_content(c)?)
+        }
+        codes::BIN_8 | codes::BIN_16 | codes::BIN_32 => {
+            ValueRef::Binary(dec.decode_bytes_ref_content(c)?)
+        }
+        _ if codes::is_fixed_num(c) => ValueRef::Int(dec.read_int(c)?),
+        _ if codes::is_fixed_string(c) => ValueRef::String(dec.decode_str_ref_content(c)?),
+        _ if codes::is_fixed_array(c) || c == codes::ARRAY_16 || c == codes::ARRAY_32 => {
+            let arraylen = dec.array_len(c)?;
+            let mut result: Vec<ValueRef<'a>> = Vec::new();
+            for _ in 0..arraylen {
+                result.push(decode_to_value_ref_inner(dec)?);
+            }
+            ValueRef::Array(result)
+        }
+        _ if codes::is_fixed_map(c) || c == codes::MAP_16 || c == codes::MAP_32 => {
+            let maplen = dec.map_len(c)?;
+            let mut pairs: Vec<(ValueRef<'a>, ValueRef<'a>)> = Vec::new();
+            for _ in 0..maplen {
+                let mapkey = decode_to_value_ref_inner(dec)?;
+                let mapvalue = decode_to_value_ref_inner(dec)?;
+                pairs.push((mapkey, mapvalue));
+            }
+
+            let keys = pairs.iter().map(|(k, _)| match k {
+                ValueRef::String(k) => Some(*k),
+                _ => None,
+            });
+            if !is_object_keys(keys) {
+                return Ok(ValueRef::Map(pairs));
+            }
+            let mut members = Vec::with_capacity(pairs.len());
+            for (k, v) in pairs {
+                if let ValueRef::String(k) = k {
+                    members.push((k, v));
+                }
+            }
+            ValueRef::Object(members)
+        }
+        _ if codes::is_ext(c) => {
+            let (type_id, data) = dec.read_ext_ref(c)?;
+            if type_id == time::TIME_EXT_ID {
+                let (secs, nanos) = time::decode_timestamp(data)?;
+                return Ok(ValueRef::Timestamp { secs, nanos });
+            }
+            ValueRef::Ext { type_id, data }
+        }
+        _ => return Err(RMError::InvalidCode(c)),
+    };
+    Ok(result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -510,26 +657,26 @@ mod tests {
         let buf = vec![0xc3];
         let mut dec = Decoder::new(&buf);
         let result = dec.decode_bool().unwrap();
-        assert_eq!(result, true);
+        assert!(result);
 
         let buf = vec![0xc2];
         let mut dec = Decoder::new(&buf);
         let result = dec.decode_bool().unwrap();
-        assert_eq!(result, false);
+        assert!(!result);
 
         let buf = vec![0xc2, 0xc3];
         let mut dec = Decoder::new(&buf);
         let result1 = dec.decode_bool().unwrap();
         let result2 = dec.decode_bool().unwrap();
-        assert_eq!(result1, false);
-        assert_eq!(result2, true);
+        assert!(!result1);
+        assert!(result2);
 
         let buf = vec![0xc3, 0xc2];
         let mut dec = Decoder::new(&buf);
         let result1 = dec.decode_bool().unwrap();
         let result2 = dec.decode_bool().unwrap();
-        assert_eq!(result1, true);
-        assert_eq!(result2, false);
+        assert!(result1);
+        assert!(!result2);
     }
 
     #[test]
@@ -569,6 +716,37 @@ mod tests {
         assert_eq!(result, expectoutput);
     }
 
+    #[test]
+    fn test_decode_empty_bytes() {
+        let buf = vec![0xC4, 0x00, 0xC4, 0x00];
+        let mut dec = Decoder::new(&buf);
+        assert_eq!(dec.decode_bytes().unwrap(), Vec::<u8>::new());
+        assert_eq!(dec.decode_bytes().unwrap(), Vec::<u8>::new());
+        assert_eq!(
+            decode_to_value(&[0xC4, 0x00]).unwrap(),
+            Value::Binary(Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_decode_bytes_bad_len() {
+        // BIN_32 lengths of 2^31 and up, and lengths past the end of the
+        // input, must fail instead of decoding as empty or allocating.
+        for buf in [
+            vec![0xC6, 0xFF, 0xFF, 0xFF, 0xFF, 0x01],
+            vec![0xC6, 0x80, 0x00, 0x00, 0x00, 0x01],
+            vec![0xC6, 0x00, 0x00, 0x00, 0x02, 0x01],
+            vec![0xDB, 0xFF, 0xFF, 0xFF, 0xFF, 0x61],
+        ]
+        .iter()
+        {
+            assert!(decode_to_value(buf).is_err(), "{:x?}", buf);
+            assert!(decode_to_value_ref(buf).is_err(), "{:x?}", buf);
+        }
+        let mut dec = Decoder::new(&[0xC5, 0x00, 0x03, 0x01]);
+        assert!(dec.decode_bytes().is_err());
+    }
+
     #[test]
     fn test_decode_integer() {
         let buf = vec![0xD3, 0xFF, 0xFF, 0xFF, 0xCE, 0x00, 0x00, 0x00, 0x64];
@@ -676,4 +854,274 @@ mod tests {
         let expectoutput = 1.0 / 3.0;
         assert_eq!(result, expectoutput);
     }
+
+    #[test]
+    fn test_decode_to_value_numbers() {
+        let buf = vec![0x05];
+        match decode_to_value(&buf).unwrap() {
+            Value::Int(n) => assert_eq!(n, 5),
+            v => panic!("unexpected value {:?}", v),
+        }
+
+        let buf = vec![0xD0, 0x9C];
+        match decode_to_value(&buf).unwrap() {
+            Value::Int(n) => assert_eq!(n, -100),
+            v => panic!("unexpected value {:?}", v),
+        }
+
+        let buf = vec![0xCF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        match decode_to_value(&buf).unwrap() {
+            Value::UInt(n) => assert_eq!(n, u64::MAX),
+            v => panic!("unexpected value {:?}", v),
+        }
+
+        let buf = vec![0xCA, 0x3F, 0x9E, 0x06, 0x10];
+        match decode_to_value(&buf).unwrap() {
+            Value::F32(n) => assert_eq!(n, 1.23456),
+            v => panic!("unexpected value {:?}", v),
+        }
+
+        let buf = vec![0xCB, 0x3F, 0xF3, 0xC0, 0xC1, 0xFC, 0x8F, 0x32, 0x38];
+        match decode_to_value(&buf).unwrap() {
+            Value::F64(n) => assert_eq!(n, 1.23456),
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_decode_to_value_binary() {
+        let buf = vec![0xC4, 0x03, 0xff, 0xfe, 0x00];
+        match decode_to_value(&buf).unwrap() {
+            Value::Binary(b) => assert_eq!(b, vec![0xff, 0xfe, 0x00]),
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_decode_to_value_ext() {
+        let buf = vec![0xD5, 0x05, 0x01, 0x02];
+        match decode_to_value(&buf).unwrap() {
+            Value::Ext { type_id, data } => {
+                assert_eq!(type_id, 5);
+                assert_eq!(data, vec![0x01, 0x02]);
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+
+        let buf = vec![0x92, 0xC7, 0x03, 0x80, 0x01, 0x02, 0x03, 0xC3];
+        match decode_to_value(&buf).unwrap() {
+            Value::Array(arr) => {
+                assert_eq!(arr.len(), 2);
+                match &arr[0] {
+                    Value::Ext { type_id, data } => {
+                        assert_eq!(*type_id, -128);
+                        assert_eq!(data, &vec![0x01, 0x02, 0x03]);
+                    }
+                    v => panic!("unexpected value {:?}", v),
+                }
+                match &arr[1] {
+                    Value::Bool(b) => assert!(*b),
+                    v => panic!("unexpected value {:?}", v),
+                }
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+
+        let buf = vec![0xC7, 0x00, 0x07];
+        let v = decode_to_value(&buf).unwrap();
+        assert_eq!(crate::encode::encode_value(&v).unwrap(), buf);
+    }
+
+    #[test]
+    fn test_decode_to_value_ext_bad_len() {
+        for buf in [
+            vec![0xC9, 0xFF, 0xFF, 0xFF, 0xFF, 0x01],
+            vec![0xC9, 0x7F, 0xFF, 0xFF, 0xFF, 0x01, 0x00],
+            vec![0xC7, 0x05, 0x01, 0x00],
+            vec![0xD8, 0x01, 0x00],
+            vec![0xDD, 0xFF, 0xFF, 0xFF, 0xFF, 0xC0],
+            vec![0xDF, 0x80, 0x00, 0x00, 0x00, 0xC0, 0xC0],
+        ]
+        .iter()
+        {
+            assert!(decode_to_value(buf).is_err(), "{:x?}", buf);
+            assert!(decode_to_value_ref(buf).is_err(), "{:x?}", buf);
+        }
+    }
+
+    #[test]
+    fn test_decode_time() {
+        use std::time::Duration;
+
+        let t = SystemTime::UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789);
+        let mut enc = crate::encode::Encoder::new();
+        enc.encode_time(t).unwrap();
+        let mut dec = Decoder::new(&enc.buf);
+        assert_eq!(dec.decode_time().unwrap(), t);
+    }
+
+    #[test]
+    fn test_decode_to_value_timestamp() {
+        let buf = vec![0xD6, 0xFF, 0x00, 0x00, 0x00, 0x01];
+        match decode_to_value(&buf).unwrap() {
+            Value::Timestamp { secs, nanos } => {
+                assert_eq!(secs, 1);
+                assert_eq!(nanos, 0);
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+
+        for (secs, nanos, len) in &[(1, 0, 6), (1, 1, 10), (-1, 0, 15)] {
+            let v = Value::Timestamp {
+                secs: *secs,
+                nanos: *nanos,
+            };
+            let buf = crate::encode::encode_value(&v).unwrap();
+            assert_eq!(buf.len(), *len);
+            match decode_to_value(&buf).unwrap() {
+                Value::Timestamp { secs: s, nanos: n } => {
+                    assert_eq!(s, *secs);
+                    assert_eq!(n, *nanos);
+                }
+                v => panic!("unexpected value {:?}", v),
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_to_value_non_string_keys() {
+        // {1: "a", 2: "b"}
+        let buf = vec![0x82, 0x01, 0xA1, 0x61, 0x02, 0xA1, 0x62];
+        let v = decode_to_value(&buf).unwrap();
+        match &v {
+            Value::Map(pairs) => {
+                assert_eq!(pairs.len(), 2);
+                match (&pairs[0].0, &pairs[0].1) {
+                    (Value::Int(1), Value::String(s)) => assert_eq!(s, "a"),
+                    p => panic!("unexpected pair {:?}", p),
+                }
+                match (&pairs[1].0, &pairs[1].1) {
+                    (Value::Int(2), Value::String(s)) => assert_eq!(s, "b"),
+                    p => panic!("unexpected pair {:?}", p),
+                }
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+        assert_eq!(crate::encode::encode_value(&v).unwrap(), buf);
+
+        // {nil: true, "k": false}
+        let buf = vec![0x82, 0xC0, 0xC3, 0xA1, 0x6B, 0xC2];
+        let v = decode_to_value(&buf).unwrap();
+        match &v {
+            Value::Map(pairs) => assert_eq!(pairs.len(), 2),
+            v => panic!("unexpected value {:?}", v),
+        }
+        assert_eq!(crate::encode::encode_value(&v).unwrap(), buf);
+    }
+
+    #[test]
+    fn test_decode_to_value_map_normalisation() {
+        use crate::encode::encode_value;
+
+        // Maps keyed by distinct strings, and empty maps, become objects.
+        let m = Value::Map(vec![(Value::String("k".to_string()), Value::Int(1))]);
+        let v = decode_to_value(&encode_value(&m).unwrap()).unwrap();
+        assert_eq!(v.get("k"), Some(&Value::Int(1)));
+        let v = decode_to_value(&encode_value(&Value::Map(Vec::new())).unwrap()).unwrap();
+        assert_eq!(v, Value::Object(ObjectMap::new()));
+        assert_eq!(
+            decode_to_value_ref(&[0x80]).unwrap(),
+            ValueRef::Object(Vec::new())
+        );
+
+        // {"a": 1, "a": 2} keeps both entries in both decoders.
+        let buf = vec![0x82, 0xA1, 0x61, 0x01, 0xA1, 0x61, 0x02];
+        let v = decode_to_value(&buf).unwrap();
+        let a = Value::String("a".to_string());
+        assert_eq!(
+            v,
+            Value::Map(vec![(a.clone(), Value::Int(1)), (a, Value::Int(2))])
+        );
+        assert_eq!(encode_value(&v).unwrap(), buf);
+        assert_eq!(
+            decode_to_value_ref(&buf).unwrap(),
+            ValueRef::Map(vec![
+                (ValueRef::String("a"), ValueRef::Int(1)),
+                (ValueRef::String("a"), ValueRef::Int(2)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_decode_to_value_keeps_key_order() {
+        // {"b": 1, "a": 2, "c": 3}
+        let buf = vec![0x83, 0xA1, 0x62, 0x01, 0xA1, 0x61, 0x02, 0xA1, 0x63, 0x03];
+        let v = decode_to_value(&buf).unwrap();
+        match &v {
+            Value::Object(o) => {
+                let keys: Vec<&str> = o.keys().map(|k| k.as_str()).collect();
+                assert_eq!(keys, vec!["b", "a", "c"]);
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+        assert_eq!(crate::encode::encode_value(&v).unwrap(), buf);
+    }
+
+    #[test]
+    fn test_decode_to_value_compare_tree() {
+        // {"a": [1, -1, true], "b": nil}
+        let buf = vec![0x82, 0xA1, 0x61, 0x93, 0x01, 0xFF, 0xC3, 0xA1, 0x62, 0xC0];
+        let mut expected = ObjectMap::new();
+        expected.insert("b".to_string(), Value::Null);
+        expected.insert(
+            "a".to_string(),
+            Value::Array(vec![Value::UInt(1), Value::Int(-1), Value::Bool(true)]),
+        );
+        assert_eq!(decode_to_value(&buf).unwrap(), Value::Object(expected));
+    }
+
+    #[test]
+    fn test_decode_to_value_ref() {
+        // {"s": "hi", "b": bin8 [1, 2], "e": fixext1 type 5 [9], "n": [1, -1, nil]}
+        let buf = vec![
+            0x84, 0xA1, 0x73, 0xA2, 0x68, 0x69, 0xA1, 0x62, 0xC4, 0x02, 0x01, 0x02, 0xA1, 0x65,
+            0xD4, 0x05, 0x09, 0xA1, 0x6E, 0x93, 0x01, 0xFF, 0xC0,
+        ];
+        let v = decode_to_value_ref(&buf).unwrap();
+        match v.get("s") {
+            Some(ValueRef::String(s)) => {
+                assert_eq!(*s, "hi");
+                // Borrowed straight from the input buffer.
+                assert_eq!(s.as_ptr(), buf[4..].as_ptr());
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+        assert_eq!(v.get("b"), Some(&ValueRef::Binary(&[1, 2])));
+        assert_eq!(
+            v.get("e"),
+            Some(&ValueRef::Ext {
+                type_id: 5,
+                data: &[9]
+            })
+        );
+        assert_eq!(v.pointer("/n/1"), Some(&ValueRef::Int(-1)));
+        assert_eq!(v.to_value(), decode_to_value(&buf).unwrap());
+    }
+
+    #[test]
+    fn test_decode_to_value_ref_errors() {
+        // str8 claiming 5 bytes with only 2 present
+        assert!(decode_to_value_ref(&[0xD9, 0x05, 0x61, 0x62]).is_err());
+        // invalid utf-8
+        assert!(decode_to_value_ref(&[0xA1, 0xFF]).is_err());
+        assert!(decode_to_value_ref(&[0xC1]).is_err());
+
+        let mut enc = crate::encode::Encoder::new();
+        enc.encode_time(SystemTime::UNIX_EPOCH + std::time::Duration::new(7, 8))
+            .unwrap();
+        assert_eq!(
+            decode_to_value_ref(&enc.buf).unwrap(),
+            ValueRef::Timestamp { secs: 7, nanos: 8 }
+        );
+    }
 }
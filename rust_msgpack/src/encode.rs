@@ -11,6 +11,12 @@ pub struct Encoder {
     pub buf: Vec<u8>,
 }
 
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Encoder {
     pub fn new() -> Encoder {
         Encoder { buf: Vec::new() }
@@ -78,9 +84,7 @@ impl Encoder {
 
 impl Encoder {
     pub fn encode_string(&mut self, v: &str) -> Result<(), RMError> {
-        if let Err(error) = self.encode_str_len(v.len() as i32) {
-            return Err(error);
-        }
+        self.encode_str_len(v.len() as i32)?;
         self.write_string(v)
     }
 
@@ -100,9 +104,7 @@ impl Encoder {
 
 impl Encoder {
     pub fn encode_bytes(&mut self, v: &[u8]) -> Result<(), RMError> {
-        if let Err(error) = self.encode_bytes_len(v.len() as i32) {
-            return Err(error);
-        }
+        self.encode_bytes_len(v.len() as i32)?;
         self.write(v)
     }
 
@@ -125,29 +127,29 @@ impl Encoder {
         if v >= ((codes::NEG_FIXED_NUM_LOW as i8) as i64) {
             return self.write_byte(v as u8);
         }
-        if v >= (std::i8::MIN as i64) {
+        if v >= (i8::MIN as i64) {
             return self.write1(codes::INT_8, v as u8);
         }
-        if v >= (std::i16::MIN as i64) {
+        if v >= (i16::MIN as i64) {
             return self.write2(codes::INT_16, v as u16);
         }
-        if v >= (std::i32::MIN as i64) {
+        if v >= (i32::MIN as i64) {
             return self.write4(codes::INT_32, v as u32);
         }
         self.write8(codes::INT_64, v as u64)
     }
 
     pub fn encode_uint(&mut self, v: u64) -> Result<(), RMError> {
-        if v <= (std::i8::MAX as u64) {
+        if v <= (i8::MAX as u64) {
             return self.write_byte(v as u8);
         }
-        if v <= (std::u8::MAX as u64) {
+        if v <= (u8::MAX as u64) {
             return self.write1(codes::UINT_8, v as u8);
         }
-        if v <= (std::u16::MAX as u64) {
+        if v <= (u16::MAX as u64) {
             return self.write2(codes::UINT_16, v as u16);
         }
-        if v <= (std::u32::MAX as u64) {
+        if v <= (u32::MAX as u64) {
             return self.write4(codes::UINT_32, v as u32);
         }
         self.write8(codes::UINT_64, v)
@@ -215,19 +217,18 @@ impl Encoder {
     }
 }
 
+impl Encoder {
+    pub fn encode_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), RMError> {
+        self.encode_ext_len(data.len() as i32)?;
+        self.write_byte(type_id as u8)?;
+        self.write(data)
+    }
+}
+
 impl Encoder {
     pub fn encode_time(&mut self, t: SystemTime) -> Result<(), RMError> {
         let b = time::encode_time(t);
-        if let Err(error) = self.encode_ext_len(b.len() as i32) {
-            return Err(error);
-        }
-
-        const TIME_EXT_ID: i32 = -1;
-        if let Err(error) = self.write_byte(TIME_EXT_ID as u8) {
-            return Err(error);
-        }
-
-        self.write(&b)
+        self.encode_ext(time::TIME_EXT_ID, &b)
     }
 }
 
@@ -244,11 +245,22 @@ pub fn encode_value(v: &Value) -> Result<Vec<u8>, RMError> {
     match v {
         Value::Null => enc.encode_nil()?,
         Value::Bool(b) => enc.encode_bool(*b)?,
-        Value::Number(n) => {
-            let num = n.parse::<f64>().unwrap();
-            enc.encode_float64(num)?;
+        Value::Int(n) => enc.encode_int(*n)?,
+        Value::UInt(n) => enc.encode_uint(*n)?,
+        Value::F32(n) => enc.encode_float32(*n)?,
+        Value::F64(n) => enc.encode_float64(*n)?,
+        Value::String(s) => enc.encode_string(s)?,
+        Value::Binary(b) => enc.encode_bytes(b)?,
+        Value::Ext { type_id, data } => enc.encode_ext(*type_id, data)?,
+        Value::Timestamp { secs, nanos } => {
+            // The decoder rejects these, and from 2^30 on they do not even
+            // fit the 64-bit form.
+            if *nanos >= 1_000_000_000 {
+                return Err(RMError::InvalidNanos(*nanos));
+            }
+            let b = time::encode_timestamp(*secs, *nanos);
+            enc.encode_ext(time::TIME_EXT_ID, &b)?;
         }
-        Value::String(s) => enc.encode_string(&s)?,
         Value::Array(arr) => {
             enc.encode_array_len(arr.len() as i32)?;
             for v in arr {
@@ -264,6 +276,13 @@ pub fn encode_value(v: &Value) -> Result<Vec<u8>, RMError> {
                 enc.buf.extend(subvalue);
             }
         }
+        Value::Map(pairs) => {
+            enc.encode_map_len(pairs.len() as i32)?;
+            for (key, value) in pairs {
+                enc.buf.extend(encode_value(key)?);
+                enc.buf.extend(encode_value(value)?);
+            }
+        }
     }
     Ok(enc.buf)
 }
@@ -330,7 +349,7 @@ mod tests {
     #[test]
     fn test_encode_bytes_1() {
         let mut enc = Encoder::new();
-        let input = vec![b'a', b'b'];
+        let input = [b'a', b'b'];
         enc.encode_bytes(&input[..]).unwrap();
         let expectoutput = vec![0xC4, 0x02, 0x61, 0x62];
         assert_eq!(&enc.buf, &expectoutput);
@@ -468,4 +487,81 @@ mod tests {
         ];
         assert_eq!(&enc.buf, &expectoutput);
     }
+
+    #[test]
+    fn test_encode_value_numbers() {
+        let buf = encode_value(&Value::Int(-100)).unwrap();
+        assert_eq!(&buf, &[0xD0, 0x9C]);
+
+        let buf = encode_value(&Value::UInt(200)).unwrap();
+        assert_eq!(&buf, &[0xCC, 0xC8]);
+
+        let big: u64 = (1 << 53) + 1;
+        let buf = encode_value(&Value::UInt(big)).unwrap();
+        assert_eq!(
+            &buf,
+            &[0xCF, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]
+        );
+
+        let buf = encode_value(&Value::F32(1.23456)).unwrap();
+        assert_eq!(&buf, &[0xCA, 0x3F, 0x9E, 0x06, 0x10]);
+
+        let buf = encode_value(&Value::F64(1.23456)).unwrap();
+        assert_eq!(
+            &buf,
+            &[0xCB, 0x3F, 0xF3, 0xC0, 0xC1, 0xFC, 0x8F, 0x32, 0x38]
+        );
+    }
+
+    #[test]
+    fn test_encode_value_binary() {
+        let buf = encode_value(&Value::Binary(vec![0xff, 0x00])).unwrap();
+        assert_eq!(&buf, &[0xC4, 0x02, 0xff, 0x00]);
+    }
+
+    #[test]
+    fn test_encode_ext() {
+        let mut enc = Encoder::new();
+        enc.encode_ext(5, &[0x01, 0x02]).unwrap();
+        assert_eq!(&enc.buf, &[0xD5, 0x05, 0x01, 0x02]);
+
+        let mut enc = Encoder::new();
+        enc.encode_ext(-128, &[0x01, 0x02, 0x03]).unwrap();
+        assert_eq!(&enc.buf, &[0xC7, 0x03, 0x80, 0x01, 0x02, 0x03]);
+
+        let v = Value::Ext {
+            type_id: 1,
+            data: vec![0xAA; 8],
+        };
+        let buf = encode_value(&v).unwrap();
+        assert_eq!(buf[..2], [0xD7, 0x01]);
+        assert_eq!(buf.len(), 10);
+    }
+
+    #[test]
+    fn test_encode_value_timestamp() {
+        let v = Value::Timestamp {
+            secs: 1,
+            nanos: 999_999_999,
+        };
+        let buf = encode_value(&v).unwrap();
+        assert_eq!(buf[..2], [0xD7, 0xFF]);
+
+        for nanos in &[1_000_000_000, 1 << 30, u32::MAX] {
+            let v = Value::Timestamp {
+                secs: 1,
+                nanos: *nanos,
+            };
+            match encode_value(&v) {
+                Err(RMError::InvalidNanos(n)) => assert_eq!(n, *nanos),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+
+        let nested = Value::Array(vec![Value::Timestamp {
+            secs: 0,
+            nanos: 1_000_000_000,
+        }]);
+        assert!(encode_value(&nested).is_err());
+    }
 }
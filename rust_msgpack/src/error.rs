@@ -8,6 +8,7 @@ pub enum Error {
     RWNotMatch,
     InvalidCode(u8),
     InvalidExtLen(i32),
+    InvalidNanos(u32),
 }
 
 impl fmt::Display for Error {
@@ -18,6 +19,7 @@ impl fmt::Display for Error {
             Error::RWNotMatch => f.write_str("RWNotMatch"),
             Error::InvalidCode(c) => write!(f, "InvalidCode: {}", c),
             Error::InvalidExtLen(c) => write!(f, "InvalidExtLen: {}", c),
+            Error::InvalidNanos(n) => write!(f, "InvalidNanos: {}", n),
         }
     }
 }
@@ -30,14 +32,19 @@ impl StdError for Error {
             Error::RWNotMatch => "read write not match with expected number",
             Error::InvalidCode(_) => "Invalid codes::Code",
             Error::InvalidExtLen(_) => "Invalid Ext Len",
+            Error::InvalidNanos(_) => "Timestamp nanoseconds out of range",
         }
     }
 }
 
 impl From<std::string::FromUtf8Error> for Error {
-    fn from(e: std::string::FromUtf8Error) -> Self {
-        match e {
-            _ => Error::InternalError,
-        }
+    fn from(_e: std::string::FromUtf8Error) -> Self {
+        Error::InternalError
+    }
+}
+
+impl From<std::str::Utf8Error> for Error {
+    fn from(_e: std::str::Utf8Error) -> Self {
+        Error::InternalError
     }
 }
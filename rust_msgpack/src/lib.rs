@@ -4,6 +4,8 @@ pub mod codes;
 pub mod decode;
 pub mod encode;
 pub mod error;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod time;
 pub mod utils;
 
@@ -0,0 +1,611 @@
+//! Helpers for property tests, enabled by the `testing` feature.
+//!
+//! `Generator` produces random `Value` trees and msgpack byte strings from a
+//! seed, so a failing case can be replayed by reusing the seed. Everything
+//! it generates survives an encode/decode round trip: floats are never NaN
+//! and extension payloads never use the timestamp type.
+//!
+//! `assert_roundtrip!` encodes a value, decodes it again and on a mismatch
+//! panics with the paths at which the two trees differ. Since msgpack has a
+//! single map type, the decoded value is compared with `normalize(v)`
+//! rather than `v` itself.
+
+use crate::decode::{decode_to_value, is_object_keys};
+use crate::encode::encode_value;
+use crate::time::TIME_EXT_ID;
+use std::fmt;
+use value::into_value::IntoValue;
+use value::object_map::ObjectMap;
+use value::value::{Value, ValueKind};
+use value::visit::{path_to_pointer, PathSegment};
+
+/// Checks that a value survives `encode_value` followed by
+/// `decode_to_value`. Takes a `Value` or anything implementing `IntoValue`,
+/// and an optional message like `assert_eq!`.
+///
+/// ```
+/// use rust_msgpack::assert_roundtrip;
+/// use rust_msgpack::testing::Generator;
+///
+/// let mut gen = Generator::new(7);
+/// for _ in 0..10 {
+///     let v = gen.value();
+///     assert_roundtrip!(v, "seed 7");
+/// }
+/// ```
+#[macro_export]
+macro_rules! assert_roundtrip {
+    ($v:expr $(,)?) => {
+        if let Err(msg) = $crate::testing::check_roundtrip(&$v) {
+            panic!("{}", msg);
+        }
+    };
+    ($v:expr, $($arg:tt)+) => {
+        if let Err(msg) = $crate::testing::check_roundtrip(&$v) {
+            panic!("{}: {}", format_args!($($arg)+), msg);
+        }
+    };
+}
+
+const KINDS: [ValueKind; 13] = [
+    ValueKind::Null,
+    ValueKind::Bool,
+    ValueKind::Int,
+    ValueKind::UInt,
+    ValueKind::F32,
+    ValueKind::F64,
+    ValueKind::String,
+    ValueKind::Binary,
+    ValueKind::Ext,
+    ValueKind::Timestamp,
+    ValueKind::Array,
+    ValueKind::Object,
+    ValueKind::Map,
+];
+
+fn is_container(kind: ValueKind) -> bool {
+    matches!(kind, ValueKind::Array | ValueKind::Object | ValueKind::Map)
+}
+
+/// Random `Value` and msgpack generator.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    state: u64,
+    max_depth: usize,
+    max_len: usize,
+    weights: [u32; 13],
+}
+
+impl Generator {
+    /// Every kind equally likely, containers nested up to 3 levels deep and
+    /// at most 8 elements, characters or bytes per value.
+    pub fn new(seed: u64) -> Generator {
+        Generator {
+            state: seed,
+            max_depth: 3,
+            max_len: 8,
+            weights: [1; 13],
+        }
+    }
+
+    /// Nests containers at most `depth` levels deep; 0 generates scalars
+    /// only.
+    pub fn max_depth(mut self, depth: usize) -> Generator {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Caps the number of elements in arrays, objects and maps and the
+    /// length of strings, binaries and extension payloads.
+    pub fn max_len(mut self, len: usize) -> Generator {
+        self.max_len = len;
+        self
+    }
+
+    /// Sets how often `kind` is picked relative to the other kinds; 0 never
+    /// picks it. Map keys and the leaves below the depth limit may still
+    /// use kinds with weight 0 when no other scalar is allowed.
+    pub fn weight(mut self, kind: ValueKind, weight: u32) -> Generator {
+        self.weights[kind as usize] = weight;
+        self
+    }
+
+    /// Picks only the given kinds, with equal weight.
+    pub fn only(mut self, kinds: &[ValueKind]) -> Generator {
+        self.weights = [0; 13];
+        for kind in kinds {
+            self.weights[*kind as usize] = 1;
+        }
+        self
+    }
+
+    /// A random value tree.
+    pub fn value(&mut self) -> Value {
+        self.value_at(0)
+    }
+
+    /// The msgpack encoding of a random value tree.
+    pub fn msgpack(&mut self) -> Vec<u8> {
+        let v = self.value();
+        encode_value(&v).expect("generated values always encode")
+    }
+
+    fn value_at(&mut self, depth: usize) -> Value {
+        let containers = depth < self.max_depth;
+        let kind = self
+            .pick(|k| containers || !is_container(k))
+            .unwrap_or(ValueKind::Null);
+        match kind {
+            ValueKind::Array => {
+                let len = self.random_len();
+                Value::Array((0..len).map(|_| self.value_at(depth + 1)).collect())
+            }
+            ValueKind::Object => {
+                let len = self.random_len();
+                let mut o = ObjectMap::with_capacity(len);
+                for _ in 0..len {
+                    let k = self.string();
+                    let v = self.value_at(depth + 1);
+                    o.insert(k, v);
+                }
+                Value::Object(o)
+            }
+            ValueKind::Map => {
+                let len = self.random_len();
+                let mut pairs = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let k = self.map_key();
+                    let v = self.value_at(depth + 1);
+                    pairs.push((k, v));
+                }
+                Value::Map(pairs)
+            }
+            kind => self.scalar(kind),
+        }
+    }
+
+    fn map_key(&mut self) -> Value {
+        let kind = self.pick(|k| !is_container(k)).unwrap_or(ValueKind::Int);
+        self.scalar(kind)
+    }
+
+    fn scalar(&mut self, kind: ValueKind) -> Value {
+        match kind {
+            ValueKind::Bool => Value::Bool(self.next() & 1 == 1),
+            ValueKind::Int => Value::Int(self.int()),
+            ValueKind::UInt => Value::UInt(self.next() >> self.below(64)),
+            ValueKind::F32 => loop {
+                let f = f32::from_bits(self.next() as u32);
+                if !f.is_nan() {
+                    break Value::F32(f);
+                }
+            },
+            ValueKind::F64 => loop {
+                let f = f64::from_bits(self.next());
+                if !f.is_nan() {
+                    break Value::F64(f);
+                }
+            },
+            ValueKind::String => Value::String(self.string()),
+            ValueKind::Binary => Value::Binary(self.bytes()),
+            ValueKind::Ext => {
+                let mut type_id = self.next() as i8;
+                if type_id == TIME_EXT_ID {
+                    type_id = 0;
+                }
+                Value::Ext {
+                    type_id,
+                    data: self.bytes(),
+                }
+            }
+            ValueKind::Timestamp => Value::Timestamp {
+                secs: self.int(),
+                nanos: self.below(1_000_000_000) as u32,
+            },
+            _ => Value::Null,
+        }
+    }
+
+    // Integers of every magnitude, so each msgpack width gets exercised.
+    fn int(&mut self) -> i64 {
+        let shift = self.below(64);
+        (self.next() as i64) >> shift
+    }
+
+    fn string(&mut self) -> String {
+        let len = self.random_len();
+        (0..len)
+            .map(|_| {
+                if self.below(4) > 0 {
+                    (b' ' + self.below(95) as u8) as char
+                } else {
+                    loop {
+                        if let Some(c) = std::char::from_u32(self.below(0x11_0000) as u32) {
+                            break c;
+                        }
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn bytes(&mut self) -> Vec<u8> {
+        let len = self.random_len();
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+
+    fn pick<F>(&mut self, allowed: F) -> Option<ValueKind>
+    where
+        F: Fn(ValueKind) -> bool,
+    {
+        let mut weights = self.weights;
+        for k in KINDS.iter() {
+            if !allowed(*k) {
+                weights[*k as usize] = 0;
+            }
+        }
+        let total: u64 = weights.iter().map(|w| u64::from(*w)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut n = self.next() % total;
+        for k in KINDS.iter() {
+            let w = u64::from(weights[*k as usize]);
+            if n < w {
+                return Some(*k);
+            }
+            n -= w;
+        }
+        None
+    }
+
+    // A length from 0 to max_len inclusive.
+    fn random_len(&mut self) -> usize {
+        match self.max_len.checked_add(1) {
+            Some(n) => self.below(n),
+            None => self.next() as usize,
+        }
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    // splitmix64
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// One place where two value trees differ, found by `diff`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    /// JSON Pointer to the node.
+    pub pointer: String,
+    /// The node in the left tree, `None` if it only exists on the right.
+    pub left: Option<Value>,
+    /// The node in the right tree, `None` if it only exists on the left.
+    pub right: Option<Value>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "(root)"
+        } else {
+            &self.pointer
+        };
+        match (&self.left, &self.right) {
+            (Some(l), Some(r)) => write!(
+                f,
+                "{}: {} ({}) != {} ({})",
+                pointer,
+                l,
+                l.kind(),
+                r,
+                r.kind()
+            ),
+            (Some(l), None) => write!(f, "{}: {} missing on the right", pointer, l),
+            (None, Some(r)) => write!(f, "{}: {} missing on the left", pointer, r),
+            (None, None) => write!(f, "{}: equal", pointer),
+        }
+    }
+}
+
+/// The nodes at which `left` and `right` differ, outermost first. Objects
+/// and maps are matched by key, arrays by index; nodes of different kinds
+/// are reported whole.
+pub fn diff(left: &Value, right: &Value) -> Vec<Difference> {
+    let mut result = Vec::new();
+    let mut path = Vec::new();
+    diff_inner(left, right, &mut path, &mut result);
+    result
+}
+
+fn diff_inner<'a>(
+    left: &'a Value,
+    right: &'a Value,
+    path: &mut Vec<PathSegment<'a>>,
+    result: &mut Vec<Difference>,
+) {
+    if left == right {
+        return;
+    }
+    match (left, right) {
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                path.push(PathSegment::Index(i));
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => diff_inner(x, y, path, result),
+                    (x, y) => only(result, path, x, y),
+                }
+                path.pop();
+            }
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            for (k, x) in a.iter() {
+                path.push(PathSegment::Key(k));
+                match b.get(k) {
+                    Some(y) => diff_inner(x, y, path, result),
+                    None => only(result, path, Some(x), None),
+                }
+                path.pop();
+            }
+            for (k, y) in b.iter() {
+                if !a.contains_key(k) {
+                    path.push(PathSegment::Key(k));
+                    only(result, path, None, Some(y));
+                    path.pop();
+                }
+            }
+        }
+        (Value::Map(a), Value::Map(b)) => {
+            let find = |pairs: &'a [(Value, Value)], key: &Value| {
+                pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+            };
+            for (k, x) in a {
+                path.push(PathSegment::MapKey(k));
+                match find(b, k) {
+                    Some(y) => diff_inner(x, y, path, result),
+                    None => only(result, path, Some(x), None),
+                }
+                path.pop();
+            }
+            for (k, y) in b {
+                if find(a, k).is_none() {
+                    path.push(PathSegment::MapKey(k));
+                    only(result, path, None, Some(y));
+                    path.pop();
+                }
+            }
+        }
+        _ => only(result, path, Some(left), Some(right)),
+    }
+}
+
+fn only(
+    result: &mut Vec<Difference>,
+    path: &[PathSegment<'_>],
+    left: Option<&Value>,
+    right: Option<&Value>,
+) {
+    result.push(Difference {
+        pointer: path_to_pointer(path),
+        left: left.cloned(),
+        right: right.cloned(),
+    });
+}
+
+/// `v` as `decode_to_value` returns it after `encode_value`: a `Map` whose
+/// keys are distinct strings, the empty map included, becomes an `Object`.
+/// Applies at every level of the tree.
+pub fn normalize(v: &Value) -> Value {
+    match v {
+        Value::Array(arr) => Value::Array(arr.iter().map(normalize).collect()),
+        Value::Object(o) => {
+            Value::Object(o.iter().map(|(k, v)| (k.clone(), normalize(v))).collect())
+        }
+        Value::Map(pairs) => {
+            if is_object_keys(pairs.iter().map(|(k, _)| k.as_str())) {
+                let mut o = ObjectMap::with_capacity(pairs.len());
+                for (k, v) in pairs {
+                    if let Value::String(k) = k {
+                        o.insert(k.clone(), normalize(v));
+                    }
+                }
+                Value::Object(o)
+            } else {
+                Value::Map(
+                    pairs
+                        .iter()
+                        .map(|(k, v)| (normalize(k), normalize(v)))
+                        .collect(),
+                )
+            }
+        }
+        v => v.clone(),
+    }
+}
+
+/// Encodes `v`, decodes the bytes and compares the result with
+/// `normalize(v)`. The error describes what went wrong, listing every
+/// difference on a mismatch.
+pub fn check_roundtrip<T: IntoValue + ?Sized>(v: &T) -> Result<(), String> {
+    let original = v.into_value();
+    let bytes =
+        encode_value(&original).map_err(|e| format!("encoding {} failed: {}", original, e))?;
+    let decoded =
+        decode_to_value(&bytes).map_err(|e| format!("decoding {} failed: {}", original, e))?;
+    let expected = normalize(&original);
+    if decoded == expected {
+        return Ok(());
+    }
+    let mut msg = format!(
+        "round trip changed the value\n original: {}\n expected: {}\n  decoded: {}\ndifferences (expected != decoded):",
+        original, expected, decoded
+    );
+    for d in diff(&expected, &decoded) {
+        msg += &format!("\n  {}", d);
+    }
+    Err(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use value::value;
+
+    #[test]
+    fn test_generated_values_round_trip() {
+        for seed in 0..200 {
+            let mut gen = Generator::new(seed).max_depth(4);
+            for _ in 0..5 {
+                let v = gen.value();
+                assert_roundtrip!(v, "seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generator_config() {
+        let a = Generator::new(42).value();
+        let b = Generator::new(42).value();
+        assert_eq!(a, b);
+
+        let mut gen = Generator::new(1).max_depth(0);
+        for _ in 0..100 {
+            let v = gen.value();
+            assert!(!v.is_array() && !v.is_object() && !v.is_map(), "{}", v);
+        }
+
+        let mut gen = Generator::new(2)
+            .only(&[ValueKind::Array, ValueKind::String])
+            .max_depth(2)
+            .max_len(3);
+        for _ in 0..100 {
+            let v = gen.value();
+            assert!(v.is_array() || v.is_string(), "{}", v);
+            if let Value::Array(arr) = &v {
+                assert!(arr.len() <= 3);
+            }
+        }
+
+        let mut gen = Generator::new(3).only(&[ValueKind::Map]).max_depth(1);
+        for _ in 0..100 {
+            match gen.value() {
+                Value::Map(pairs) => assert!(pairs.len() <= 8),
+                v => panic!("unexpected value {}", v),
+            }
+        }
+    }
+
+    #[test]
+    fn test_generator_max_len_limit() {
+        let mut gen = Generator::new(4).max_len(usize::MAX);
+        for _ in 0..100 {
+            gen.random_len();
+        }
+        let mut gen = Generator::new(5).max_len(0).max_depth(2);
+        for _ in 0..100 {
+            match gen.value() {
+                Value::Array(arr) => assert!(arr.is_empty()),
+                Value::String(s) => assert!(s.is_empty()),
+                Value::Binary(b) => assert!(b.is_empty()),
+                Value::Object(o) => assert!(o.is_empty()),
+                Value::Map(pairs) => assert!(pairs.is_empty()),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_msgpack_decodes() {
+        let mut gen = Generator::new(9);
+        for _ in 0..200 {
+            let bytes = gen.msgpack();
+            let v = decode_to_value(&bytes).unwrap();
+            assert_eq!(encode_value(&v).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = value!({"a": [1, 2, 3], "b": {"c": true}, "d": "x"});
+        let b = value!({"a": [1, 5], "b": {"c": "true"}, "e": null});
+        let lines: Vec<String> = diff(&a, &b).iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "/a/1: 2 (Int) != 5 (Int)",
+                "/a/2: 3 missing on the right",
+                "/b/c: true (Bool) != \"true\" (String)",
+                "/d: \"x\" missing on the right",
+                "/e: null missing on the left",
+            ]
+        );
+        assert!(diff(&a, &a).is_empty());
+        assert_eq!(
+            diff(&value!(1), &value!(1.0))[0].to_string(),
+            "(root): 1 (Int) != 1.0 (F64)"
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let keyed = Value::Map(vec![(value!("k"), Value::Map(Vec::new()))]);
+        assert_eq!(normalize(&keyed), value!({"k": {}}));
+        assert!(normalize(&keyed).get("k").unwrap().is_object());
+
+        let repeated = Value::Map(vec![(value!("k"), value!(1)), (value!("k"), value!(2))]);
+        assert_eq!(normalize(&repeated), repeated);
+
+        let mixed = value!([Value::Map(vec![
+            (value!(1), Value::Map(vec![(value!("a"), value!(true))])),
+            (value!("b"), value!(null)),
+        ])]);
+        assert_eq!(
+            normalize(&mixed),
+            value!([Value::Map(vec![
+                (value!(1), value!({"a": true})),
+                (value!("b"), value!(null)),
+            ])])
+        );
+    }
+
+    #[test]
+    fn test_check_roundtrip_reports() {
+        assert_eq!(check_roundtrip(&value!({"a": [1, "b"]})), Ok(()));
+        assert_eq!(check_roundtrip(&vec![1u8, 2, 3]), Ok(()));
+        let v = Value::Map(vec![(value!("k"), Value::Map(Vec::new()))]);
+        assert_eq!(check_roundtrip(&v), Ok(()));
+
+        // An extension with the timestamp type comes back as a timestamp.
+        let v = value!({"t": (Value::Ext {
+            type_id: TIME_EXT_ID,
+            data: vec![0, 0, 0, 1],
+        })});
+        let err = check_roundtrip(&v).unwrap_err();
+        assert!(
+            err.contains("differences (expected != decoded):"),
+            "{}",
+            err
+        );
+        assert!(err.contains("\n  /t: "), "{}", err);
+        assert!(err.contains("(Ext) != "), "{}", err);
+        assert!(err.ends_with("(Timestamp)"), "{}", err);
+    }
+
+    #[test]
+    #[should_panic(expected = "case 1: round trip changed the value")]
+    fn test_assert_roundtrip_panics() {
+        let v = Value::Ext {
+            type_id: TIME_EXT_ID,
+            data: vec![0, 0, 0, 1],
+        };
+        assert_roundtrip!(v, "case {}", 1);
+    }
+}
@@ -1,28 +1,99 @@
 use crate::binary;
+use crate::error::Error as RMError;
 use std::time::SystemTime;
+use value::time::split_time;
+
+pub const TIME_EXT_ID: i8 = -1;
 
 pub fn encode_time(t: SystemTime) -> Vec<u8> {
-    match t.duration_since(SystemTime::UNIX_EPOCH) {
-        Ok(n) => {
-            let secs = n.as_secs();
-            if secs >> 34 == 0 {
-                let data = ((n.subsec_nanos() as u64) << 34) | secs;
-                if data & 0xffffffff00000000 == 0 {
-                    let mut b = vec![0; 4];
-                    binary::BigEndian::put_uint32(&mut b, data as u32);
-                    return b;
-                } else {
-                    let mut b = vec![0; 8];
-                    binary::BigEndian::put_uint64(&mut b, data);
-                    return b;
-                }
-            }
-
-            let mut b = vec![0; 12];
-            binary::BigEndian::put_uint32(&mut b, n.subsec_nanos());
-            binary::BigEndian::put_uint64(&mut b[4..], secs);
+    let (secs, nanos) = split_time(t);
+    encode_timestamp(secs, nanos)
+}
+
+pub fn encode_timestamp(secs: i64, nanos: u32) -> Vec<u8> {
+    if secs >> 34 == 0 {
+        let data = ((nanos as u64) << 34) | (secs as u64);
+        if data & 0xffffffff00000000 == 0 {
+            let mut b = vec![0; 4];
+            binary::BigEndian::put_uint32(&mut b, data as u32);
             return b;
+        } else {
+            let mut b = vec![0; 8];
+            binary::BigEndian::put_uint64(&mut b, data);
+            return b;
+        }
+    }
+
+    let mut b = vec![0; 12];
+    binary::BigEndian::put_uint32(&mut b, nanos);
+    binary::BigEndian::put_uint64(&mut b[4..], secs as u64);
+    b
+}
+
+pub fn decode_timestamp(b: &[u8]) -> Result<(i64, u32), RMError> {
+    let (secs, nanos) = match b.len() {
+        4 => {
+            let secs = binary::BigEndian::uint32(b);
+            (secs as i64, 0)
+        }
+        8 => {
+            let data = binary::BigEndian::uint64(b);
+            let nanos = (data >> 34) as u32;
+            let secs = data & 0x00000003ffffffff;
+            (secs as i64, nanos)
+        }
+        12 => {
+            let nanos = binary::BigEndian::uint32(b);
+            let secs = binary::BigEndian::uint64(&b[4..]);
+            (secs as i64, nanos)
+        }
+        _ => return Err(RMError::InvalidExtLen(b.len() as i32)),
+    };
+    if nanos >= 1_000_000_000 {
+        return Err(RMError::InvalidNanos(nanos));
+    }
+    Ok((secs, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_forms() {
+        assert_eq!(encode_timestamp(1, 0), vec![0, 0, 0, 1]);
+        assert_eq!(decode_timestamp(&[0, 0, 0, 1]).unwrap(), (1, 0));
+
+        let b = encode_timestamp(1, 500);
+        assert_eq!(b.len(), 8);
+        assert_eq!(decode_timestamp(&b).unwrap(), (1, 500));
+
+        let b = encode_timestamp(-1, 999_999_999);
+        assert_eq!(b.len(), 12);
+        assert_eq!(decode_timestamp(&b).unwrap(), (-1, 999_999_999));
+
+        let b = encode_timestamp(1 << 34, 0);
+        assert_eq!(b.len(), 12);
+        assert_eq!(decode_timestamp(&b).unwrap(), (1 << 34, 0));
+
+        assert!(decode_timestamp(&[0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_timestamp_nanos_out_of_range() {
+        // 64-bit form with all 30 nanosecond bits set.
+        let b = [0xFF, 0xFF, 0xFF, 0xFC, 0, 0, 0, 1];
+        match decode_timestamp(&b) {
+            Err(RMError::InvalidNanos(n)) => assert_eq!(n, 1_073_741_823),
+            r => panic!("unexpected result {:?}", r),
         }
-        Err(_) => panic!("SystemTime before UNIX EPOCH!"),
+
+        let mut b = encode_timestamp(-1, 0);
+        binary::BigEndian::put_uint32(&mut b, 1_000_000_000);
+        assert!(decode_timestamp(&b).is_err());
+
+        let mut buf = vec![0xD7, 0xFF];
+        buf.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFC, 0, 0, 0, 1]);
+        assert!(crate::decode::decode_to_value(&buf).is_err());
     }
 }
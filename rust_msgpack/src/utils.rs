@@ -1,19 +1,19 @@
 use std::cmp::min;
 
 pub fn float32bits(f: f32) -> u32 {
-    unsafe { std::mem::transmute(f) }
+    f32::to_bits(f)
 }
 
 pub fn float32frombits(b: u32) -> f32 {
-    unsafe { std::mem::transmute(b) }
+    f32::from_bits(b)
 }
 
 pub fn float64bits(f: f64) -> u64 {
-    unsafe { std::mem::transmute(f) }
+    f64::to_bits(f)
 }
 
 pub fn float64frombits(b: u64) -> f64 {
-    unsafe { std::mem::transmute(b) }
+    f64::from_bits(b)
 }
 
 pub fn slice_copy<T>(dst: &mut [T], src: &[T]) -> i64
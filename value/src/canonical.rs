@@ -0,0 +1,248 @@
+//! Canonical form and content digests.
+//!
+//! Two values that compare equal have the same canonical form and the same
+//! digest, whatever order their object members or map entries were inserted
+//! in and whichever number types they were built from.
+//!
+//! The canonical form:
+//! - sorts object members by key, comparing the UTF-8 bytes, and map entries
+//!   by key using the `Ord` for `Value`;
+//! - stores an integer as `Int` when it fits in `i64` and as `UInt` otherwise;
+//! - stores a float as `F32` when that holds it exactly and as `F64`
+//!   otherwise, with `-0.0` written as `0.0` and every NaN as the same NaN.
+//!
+//! The digest is SHA-256 over a fixed byte encoding of the tree. It does not
+//! depend on the platform, the process or the Rust version, so it can be
+//! stored and compared later.
+
+use crate::sha256::Sha256;
+use crate::value::Value;
+
+impl Value {
+    /// Rewrites this value into its canonical form, in place.
+    pub fn canonicalize(&mut self) {
+        match self {
+            Value::UInt(n) if *n <= i64::MAX as u64 => *self = Value::Int(*n as i64),
+            Value::F32(f) => *f = canonical_f32(*f),
+            Value::F64(f) => {
+                let narrow = *f as f32;
+                if f.is_nan() || narrow as f64 == *f {
+                    *self = Value::F32(canonical_f32(narrow));
+                } else {
+                    *f = canonical_f64(*f);
+                }
+            }
+            Value::Array(arr) => arr.iter_mut().for_each(Value::canonicalize),
+            Value::Object(o) => {
+                o.values_mut().for_each(Value::canonicalize);
+                o.sort_keys();
+            }
+            Value::Map(pairs) => {
+                for (k, v) in pairs.iter_mut() {
+                    k.canonicalize();
+                    v.canonicalize();
+                }
+                pairs.sort();
+            }
+            _ => {}
+        }
+    }
+
+    /// SHA-256 of the canonical encoding of this value.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        write_canonical(self, &mut hasher);
+        hasher.finish()
+    }
+
+    /// The first eight bytes of `digest()`, for use as a compact
+    /// fingerprint.
+    pub fn stable_hash(&self) -> u64 {
+        let digest = self.digest();
+        let mut head = [0; 8];
+        head.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(head)
+    }
+}
+
+fn canonical_f32(f: f32) -> f32 {
+    if f.is_nan() {
+        f32::NAN
+    } else if f == 0.0 {
+        0.0
+    } else {
+        f
+    }
+}
+
+fn canonical_f64(f: f64) -> f64 {
+    if f.is_nan() {
+        f64::NAN
+    } else if f == 0.0 {
+        0.0
+    } else {
+        f
+    }
+}
+
+// Every node starts with a tag byte. Lengths and counts are u64 and all
+// numbers are big-endian. Integers are written as i128 and floats as f64, so
+// the encoding does not depend on which variant holds a number.
+fn write_canonical(v: &Value, out: &mut Sha256) {
+    match v {
+        Value::Null => out.update(&[0]),
+        Value::Bool(b) => out.update(&[1, *b as u8]),
+        Value::Int(n) => write_int(*n as i128, out),
+        Value::UInt(n) => write_int(*n as i128, out),
+        Value::F32(f) => write_float(*f as f64, out),
+        Value::F64(f) => write_float(*f, out),
+        Value::String(s) => {
+            out.update(&[4]);
+            write_bytes(s.as_bytes(), out);
+        }
+        Value::Binary(b) => {
+            out.update(&[5]);
+            write_bytes(b, out);
+        }
+        Value::Ext { type_id, data } => {
+            out.update(&[6, *type_id as u8]);
+            write_bytes(data, out);
+        }
+        Value::Timestamp { secs, nanos } => {
+            out.update(&[7]);
+            out.update(&secs.to_be_bytes());
+            out.update(&nanos.to_be_bytes());
+        }
+        Value::Array(arr) => {
+            out.update(&[8]);
+            out.update(&(arr.len() as u64).to_be_bytes());
+            for item in arr {
+                write_canonical(item, out);
+            }
+        }
+        Value::Object(o) => {
+            let mut entries: Vec<(&String, &Value)> = o.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.update(&[9]);
+            out.update(&(entries.len() as u64).to_be_bytes());
+            for (k, v) in entries {
+                write_bytes(k.as_bytes(), out);
+                write_canonical(v, out);
+            }
+        }
+        Value::Map(pairs) => {
+            let mut sorted: Vec<&(Value, Value)> = pairs.iter().collect();
+            sorted.sort();
+            out.update(&[10]);
+            out.update(&(sorted.len() as u64).to_be_bytes());
+            for (k, v) in sorted {
+                write_canonical(k, out);
+                write_canonical(v, out);
+            }
+        }
+    }
+}
+
+fn write_int(n: i128, out: &mut Sha256) {
+    out.update(&[2]);
+    out.update(&n.to_be_bytes());
+}
+
+fn write_float(f: f64, out: &mut Sha256) {
+    out.update(&[3]);
+    out.update(&canonical_f64(f).to_bits().to_be_bytes());
+}
+
+fn write_bytes(b: &[u8], out: &mut Sha256) {
+    out.update(&(b.len() as u64).to_be_bytes());
+    out.update(b);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object_map::ObjectMap;
+
+    fn canonical(v: &Value) -> Value {
+        let mut v = v.clone();
+        v.canonicalize();
+        v
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut o = ObjectMap::new();
+        o.insert("b".to_string(), Value::UInt(1));
+        o.insert("a".to_string(), Value::F64(0.5));
+        o.insert("é".to_string(), Value::F64(0.1));
+        o.insert("Z".to_string(), Value::UInt(u64::MAX));
+        let v = canonical(&Value::Object(o));
+        match &v {
+            Value::Object(o) => {
+                let keys: Vec<&String> = o.keys().collect();
+                assert_eq!(keys, vec!["Z", "a", "b", "é"]);
+                let values: Vec<&Value> = o.values().collect();
+                assert!(matches!(values[0], Value::UInt(u64::MAX)));
+                assert!(matches!(values[1], Value::F32(f) if *f == 0.5));
+                assert!(matches!(values[2], Value::Int(1)));
+                assert!(matches!(values[3], Value::F64(f) if *f == 0.1));
+            }
+            _ => panic!("expected an object"),
+        }
+
+        match canonical(&Value::F64(-0.0)) {
+            Value::F32(f) => assert!(f.is_sign_positive()),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let m = canonical(&Value::Map(vec![
+            (Value::String("x".to_string()), Value::Null),
+            (Value::UInt(2), Value::Array(vec![Value::F64(1.0)])),
+        ]));
+        assert!(matches!(
+            &m,
+            Value::Map(pairs) if matches!(pairs[0].0, Value::Int(2))
+                && matches!(pairs[0].1, Value::Array(ref a) if matches!(a[0], Value::F32(_)))
+        ));
+    }
+
+    #[test]
+    fn test_digest_ignores_representation() {
+        let a = value!({"id": 7, "tags": ["a", "b"], "score": 1.5});
+        let mut o = ObjectMap::new();
+        o.insert("score".to_string(), Value::F32(1.5));
+        o.insert(
+            "tags".to_string(),
+            Value::Array(vec![
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+            ]),
+        );
+        o.insert("id".to_string(), Value::UInt(7));
+        let b = Value::Object(o);
+
+        assert_eq!(a.digest(), b.digest());
+        assert_eq!(a.stable_hash(), b.stable_hash());
+        assert_eq!(a.digest(), canonical(&a).digest());
+        assert_ne!(
+            a.digest(),
+            value!({"id": 8, "tags": ["a", "b"], "score": 1.5}).digest()
+        );
+        assert_ne!(value!(["ab"]).digest(), value!(["a", "b"]).digest());
+        assert_ne!(Value::Int(1).digest(), Value::F64(1.0).digest());
+        assert_eq!(
+            Value::F64(f64::NAN).digest(),
+            Value::F32(-f32::NAN).digest()
+        );
+    }
+
+    #[test]
+    fn test_digest_is_stable() {
+        assert_eq!(
+            value!({"b": [1, null], "a": true}).stable_hash(),
+            value!({"a": true, "b": [1, null]}).stable_hash()
+        );
+        // Pinned so a change to the encoding does not go unnoticed.
+        assert_eq!(Value::Null.stable_hash(), 0x6e340b9cffb37a98);
+    }
+}
@@ -0,0 +1,378 @@
+//! Equality, hashing and ordering for `Value`.
+//!
+//! Values of different kinds order as
+//! `Null < Bool < numbers < String < Binary < Ext < Timestamp < Array < Object < Map`.
+//!
+//! Numbers:
+//! - `Int` and `UInt` compare by mathematical value, so `Int(1) == UInt(1)`.
+//! - `F32` is widened to `f64`, then `F32` and `F64` compare by value.
+//!   `0.0 == -0.0`, every NaN equals every other NaN and NaN sorts above all
+//!   other numbers.
+//! - An integer never equals a float. Mixed comparisons go by mathematical
+//!   value and an integer sorts just before a float of the same value.
+//!
+//! Objects and maps are unordered: they are equal when they hold the same
+//! entries, and compare as their entries sorted by key.
+
+use crate::object_map::ObjectMap;
+use crate::value::Value;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+fn kind_rank(v: &Value) -> u8 {
+    match v {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Int(_) | Value::UInt(_) | Value::F32(_) | Value::F64(_) => 2,
+        Value::String(_) => 3,
+        Value::Binary(_) => 4,
+        Value::Ext { .. } => 5,
+        Value::Timestamp { .. } => 6,
+        Value::Array(_) => 7,
+        Value::Object(_) => 8,
+        Value::Map(_) => 9,
+    }
+}
+
+/// A number reduced to what its comparison depends on.
+pub(crate) enum Num {
+    Int(i128),
+    Float(f64),
+}
+
+fn as_num(v: &Value) -> Option<Num> {
+    match v {
+        Value::Int(n) => Some(Num::Int(*n as i128)),
+        Value::UInt(n) => Some(Num::Int(*n as i128)),
+        Value::F32(f) => Some(Num::Float(*f as f64)),
+        Value::F64(f) => Some(Num::Float(*f)),
+        _ => None,
+    }
+}
+
+fn cmp_float(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        // Neither is NaN, so partial_cmp is total and treats 0.0 == -0.0.
+        (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+    }
+}
+
+// i128 covers every integral f64 below 2^127 exactly.
+const INT_LIMIT: f64 = 170141183460469231731687303715884105728.0;
+
+fn cmp_int_float(i: i128, f: f64) -> Ordering {
+    if f.is_nan() {
+        return Ordering::Less;
+    }
+    if f >= INT_LIMIT {
+        return Ordering::Less;
+    }
+    if f < -INT_LIMIT {
+        return Ordering::Greater;
+    }
+    let t = f.trunc();
+    match i.cmp(&(t as i128)) {
+        Ordering::Equal => {
+            if f > t {
+                Ordering::Less
+            } else if f < t {
+                Ordering::Greater
+            } else {
+                // Same value: the integer goes first.
+                Ordering::Less
+            }
+        }
+        o => o,
+    }
+}
+
+pub(crate) fn cmp_num(a: &Num, b: &Num) -> Ordering {
+    match (a, b) {
+        (Num::Int(x), Num::Int(y)) => x.cmp(y),
+        (Num::Float(x), Num::Float(y)) => cmp_float(*x, *y),
+        (Num::Int(x), Num::Float(y)) => cmp_int_float(*x, *y),
+        (Num::Float(x), Num::Int(y)) => cmp_int_float(*y, *x).reverse(),
+    }
+}
+
+/// Numeric comparison that, unlike `Ord`, puts an integer level with a
+/// float of the same value. `None` when either side is not a number or is
+/// NaN.
+pub(crate) fn compare_numbers(a: &Value, b: &Value) -> Option<Ordering> {
+    let (x, y) = (as_num(a)?, as_num(b)?);
+    let same = match (&x, &y) {
+        (Num::Float(f), _) | (_, Num::Float(f)) if f.is_nan() => return None,
+        (Num::Int(i), Num::Float(f)) | (Num::Float(f), Num::Int(i)) => {
+            f.fract() == 0.0 && f.abs() < INT_LIMIT && *f as i128 == *i
+        }
+        _ => false,
+    };
+    if same {
+        Some(Ordering::Equal)
+    } else {
+        Some(cmp_num(&x, &y))
+    }
+}
+
+/// Hashes `n` consistently with `cmp_num`: every NaN hashes alike and
+/// `0.0` hashes like `-0.0`.
+pub(crate) fn hash_num<H: Hasher>(n: &Num, state: &mut H) {
+    match n {
+        Num::Int(n) => {
+            0u8.hash(state);
+            n.hash(state);
+        }
+        Num::Float(f) => {
+            1u8.hash(state);
+            let f = if f.is_nan() {
+                f64::NAN
+            } else if *f == 0.0 {
+                0.0
+            } else {
+                *f
+            };
+            f.to_bits().hash(state);
+        }
+    }
+}
+
+fn sorted_entries(o: &ObjectMap) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<(&String, &Value)> = o.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+fn sorted_pairs(pairs: &[(Value, Value)]) -> Vec<&(Value, Value)> {
+    let mut sorted: Vec<&(Value, Value)> = pairs.iter().collect();
+    sorted.sort();
+    sorted
+}
+
+impl PartialEq for ObjectMap {
+    fn eq(&self, other: &ObjectMap) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl Eq for ObjectMap {}
+
+impl PartialOrd for ObjectMap {
+    fn partial_cmp(&self, other: &ObjectMap) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ObjectMap {
+    fn cmp(&self, other: &ObjectMap) -> Ordering {
+        sorted_entries(self).cmp(&sorted_entries(other))
+    }
+}
+
+impl Hash for ObjectMap {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        sorted_entries(self).hash(state);
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Object(a), Value::Object(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => {
+                a.len() == b.len() && sorted_pairs(a) == sorted_pairs(b)
+            }
+            _ => self.cmp(other) == Ordering::Equal,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        let rank = kind_rank(self).cmp(&kind_rank(other));
+        if rank != Ordering::Equal {
+            return rank;
+        }
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Binary(a), Value::Binary(b)) => a.cmp(b),
+            (
+                Value::Ext {
+                    type_id: ta,
+                    data: da,
+                },
+                Value::Ext {
+                    type_id: tb,
+                    data: db,
+                },
+            ) => (ta, da).cmp(&(tb, db)),
+            (
+                Value::Timestamp {
+                    secs: sa,
+                    nanos: na,
+                },
+                Value::Timestamp {
+                    secs: sb,
+                    nanos: nb,
+                },
+            ) => (sa, na).cmp(&(sb, nb)),
+            (Value::Array(a), Value::Array(b)) => a.cmp(b),
+            (Value::Object(a), Value::Object(b)) => a.cmp(b),
+            (Value::Map(a), Value::Map(b)) => sorted_pairs(a).cmp(&sorted_pairs(b)),
+            (a, b) => match (as_num(a), as_num(b)) {
+                (Some(x), Some(y)) => cmp_num(&x, &y),
+                _ => Ordering::Equal,
+            },
+        }
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        kind_rank(self).hash(state);
+        match self {
+            Value::Null => {}
+            Value::Bool(b) => b.hash(state),
+            Value::Int(_) | Value::UInt(_) | Value::F32(_) | Value::F64(_) => {
+                if let Some(n) = as_num(self) {
+                    hash_num(&n, state);
+                }
+            }
+            Value::String(s) => s.hash(state),
+            Value::Binary(b) => b.hash(state),
+            Value::Ext { type_id, data } => {
+                type_id.hash(state);
+                data.hash(state);
+            }
+            Value::Timestamp { secs, nanos } => {
+                secs.hash(state);
+                nanos.hash(state);
+            }
+            Value::Array(arr) => arr.hash(state),
+            Value::Object(o) => o.hash(state),
+            Value::Map(pairs) => sorted_pairs(pairs).hash(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+
+    fn hash_of(v: &Value) -> u64 {
+        let mut h = DefaultHasher::new();
+        v.hash(&mut h);
+        h.finish()
+    }
+
+    fn object(entries: &[(&str, Value)]) -> Value {
+        Value::Object(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(Value::Int(1), Value::UInt(1));
+        assert_eq!(hash_of(&Value::Int(1)), hash_of(&Value::UInt(1)));
+        assert_ne!(Value::Int(1), Value::F64(1.0));
+        assert_eq!(Value::F32(1.5), Value::F64(1.5));
+        assert_eq!(Value::F64(0.0), Value::F64(-0.0));
+        assert_eq!(hash_of(&Value::F64(0.0)), hash_of(&Value::F64(-0.0)));
+        assert_eq!(Value::F64(f64::NAN), Value::F32(f32::NAN));
+        assert_eq!(
+            hash_of(&Value::F64(f64::NAN)),
+            hash_of(&Value::F64(-f64::NAN))
+        );
+
+        assert!(Value::Int(-1) < Value::UInt(u64::MAX));
+        assert!(Value::Int(1) < Value::F64(1.0));
+        assert!(Value::F64(1.0) < Value::Int(2));
+        assert!(Value::F64(0.5) < Value::Int(1));
+        assert!(Value::Int(-1) > Value::F64(-1.5));
+        assert!(Value::F64(f64::INFINITY) < Value::F64(f64::NAN));
+        assert!(Value::Int(i64::MAX) < Value::F64(f64::NAN));
+
+        assert_eq!(
+            compare_numbers(&Value::Int(1), &Value::F64(1.0)),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            compare_numbers(&Value::F32(0.5), &Value::UInt(1)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(compare_numbers(&Value::F64(f64::NAN), &Value::Int(1)), None);
+        assert_eq!(
+            compare_numbers(&Value::F64(1.0), &Value::F64(f64::NAN)),
+            None
+        );
+        assert_eq!(compare_numbers(&Value::Null, &Value::Int(1)), None);
+    }
+
+    #[test]
+    fn test_kind_order() {
+        let mut values = vec![
+            object(&[]),
+            Value::Array(vec![]),
+            Value::String("a".to_string()),
+            Value::Int(3),
+            Value::Bool(false),
+            Value::Null,
+        ];
+        values.sort();
+        assert_eq!(
+            values,
+            vec![
+                Value::Null,
+                Value::Bool(false),
+                Value::Int(3),
+                Value::String("a".to_string()),
+                Value::Array(vec![]),
+                object(&[]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_object_ignores_key_order() {
+        let a = object(&[("x", Value::Int(1)), ("y", Value::Int(2))]);
+        let b = object(&[("y", Value::Int(2)), ("x", Value::Int(1))]);
+        let c = object(&[("x", Value::Int(1)), ("y", Value::Int(3))]);
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(hash_of(&a), hash_of(&b));
+        assert_ne!(a, c);
+        assert!(a < c);
+
+        let m1 = Value::Map(vec![
+            (Value::Int(1), Value::Null),
+            (Value::Int(2), Value::Bool(true)),
+        ]);
+        let m2 = Value::Map(vec![
+            (Value::Int(2), Value::Bool(true)),
+            (Value::UInt(1), Value::Null),
+        ]);
+        assert_eq!(m1, m2);
+        assert_eq!(hash_of(&m1), hash_of(&m2));
+
+        let set: HashSet<Value> = vec![a, b, c].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+}
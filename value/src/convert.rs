@@ -0,0 +1,506 @@
+//! `From` and `TryFrom` conversions between `Value` and Rust types.
+//!
+//! These mirror `IntoValue` and `FromValue` with the standard traits, so
+//! values work with `.into()`, `?` and generic code. Conversions out of a
+//! `Value` return a `ConversionError` where `FromValue` would panic.
+//!
+//! - Integers convert from `Int`, `UInt` and floats without a fractional
+//!   part, as long as the value fits the target type.
+//! - Floats convert from any number.
+//! - `Vec<u8>` and other integer vectors also convert from `Binary`, one
+//!   element per byte; use `Bytes` to go to and from `Binary` itself.
+
+use crate::object_map::ObjectMap;
+use crate::time;
+use crate::value::{Bytes, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::error::Error as StdError;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    InvalidType(String),
+    OutOfRange(String),
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConversionError::InvalidType(p) => write!(f, "InvalidType: {}", p),
+            ConversionError::OutOfRange(p) => write!(f, "OutOfRange: {}", p),
+        }
+    }
+}
+
+impl StdError for ConversionError {}
+
+fn invalid_type(expected: &str, found: &Value) -> ConversionError {
+    ConversionError::InvalidType(format!("expected {}, found {}", expected, found.kind()))
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+macro_rules! from_int {
+    ($variant: ident, $wide: ty, $($type: ty), *) => {
+        $(impl From<$type> for Value {
+            fn from(n: $type) -> Value {
+                Value::$variant(n as $wide)
+            }
+        })*
+    }
+}
+
+from_int! {Int, i64, i8, i16, i32, i64, isize}
+from_int! {UInt, u64, u8, u16, u32, u64, usize}
+
+impl From<f32> for Value {
+    fn from(f: f32) -> Value {
+        Value::F32(f)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Value {
+        Value::F64(f)
+    }
+}
+
+impl From<char> for Value {
+    fn from(c: char) -> Value {
+        Value::String(c.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<Bytes> for Value {
+    fn from(b: Bytes) -> Value {
+        Value::Binary(b.0)
+    }
+}
+
+impl From<SystemTime> for Value {
+    fn from(t: SystemTime) -> Value {
+        let (secs, nanos) = time::split_time(t);
+        Value::Timestamp { secs, nanos }
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Value {
+        Value::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<ObjectMap> for Value {
+    fn from(o: ObjectMap) -> Value {
+        Value::Object(o)
+    }
+}
+
+impl<K, V, S> From<HashMap<K, V, S>> for Value
+where
+    K: Into<String>,
+    V: Into<Value>,
+{
+    fn from(m: HashMap<K, V, S>) -> Value {
+        Value::Object(m.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+impl<K, V> From<BTreeMap<K, V>> for Value
+where
+    K: Into<String>,
+    V: Into<Value>,
+{
+    fn from(m: BTreeMap<K, V>) -> Value {
+        Value::Object(m.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+impl TryFrom<&Value> for bool {
+    type Error = ConversionError;
+
+    fn try_from(v: &Value) -> Result<bool, ConversionError> {
+        v.as_bool().ok_or_else(|| invalid_type("bool", v))
+    }
+}
+
+fn float_to_i128(f: f64) -> Option<i128> {
+    if f.is_finite() && f.fract() == 0.0 {
+        Some(f as i128)
+    } else {
+        None
+    }
+}
+
+macro_rules! try_from_int {
+    ($($type: ty), *) => {
+        $(impl TryFrom<&Value> for $type {
+            type Error = ConversionError;
+
+            fn try_from(v: &Value) -> Result<$type, ConversionError> {
+                let n: Option<i128> = match v {
+                    Value::Int(n) => Some(*n as i128),
+                    Value::UInt(n) => Some(*n as i128),
+                    Value::F32(f) => float_to_i128(*f as f64),
+                    Value::F64(f) => float_to_i128(*f),
+                    _ => return Err(invalid_type(stringify!($type), v)),
+                };
+                n.and_then(|n| <$type>::try_from(n).ok()).ok_or_else(|| {
+                    ConversionError::OutOfRange(format!(
+                        "{} does not fit in {}",
+                        v,
+                        stringify!($type)
+                    ))
+                })
+            }
+        }
+
+        impl TryFrom<Value> for $type {
+            type Error = ConversionError;
+
+            fn try_from(v: Value) -> Result<$type, ConversionError> {
+                <$type>::try_from(&v)
+            }
+        })*
+    }
+}
+
+try_from_int! {i8, u8, i16, u16, i32, u32, i64, u64, isize, usize}
+
+macro_rules! try_from_float {
+    ($($type: ty), *) => {
+        $(impl TryFrom<&Value> for $type {
+            type Error = ConversionError;
+
+            fn try_from(v: &Value) -> Result<$type, ConversionError> {
+                match v {
+                    Value::Int(n) => Ok(*n as $type),
+                    Value::UInt(n) => Ok(*n as $type),
+                    Value::F32(f) => Ok(*f as $type),
+                    Value::F64(f) => Ok(*f as $type),
+                    _ => Err(invalid_type(stringify!($type), v)),
+                }
+            }
+        }
+
+        impl TryFrom<Value> for $type {
+            type Error = ConversionError;
+
+            fn try_from(v: Value) -> Result<$type, ConversionError> {
+                <$type>::try_from(&v)
+            }
+        })*
+    }
+}
+
+try_from_float! {f32, f64}
+
+impl TryFrom<&Value> for char {
+    type Error = ConversionError;
+
+    fn try_from(v: &Value) -> Result<char, ConversionError> {
+        let s = v.as_str().ok_or_else(|| invalid_type("char", v))?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(ConversionError::InvalidType(format!(
+                "expected char, found a string of {} characters",
+                s.chars().count()
+            ))),
+        }
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = ConversionError;
+
+    fn try_from(v: &Value) -> Result<String, ConversionError> {
+        v.as_str()
+            .map(str::to_string)
+            .ok_or_else(|| invalid_type("String", v))
+    }
+}
+
+impl TryFrom<&Value> for Bytes {
+    type Error = ConversionError;
+
+    fn try_from(v: &Value) -> Result<Bytes, ConversionError> {
+        match v {
+            Value::Binary(b) => Ok(Bytes(b.clone())),
+            Value::String(s) => Ok(Bytes(s.clone().into_bytes())),
+            _ => Err(invalid_type("Bytes", v)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for SystemTime {
+    type Error = ConversionError;
+
+    fn try_from(v: &Value) -> Result<SystemTime, ConversionError> {
+        match v {
+            Value::Timestamp { secs, nanos } => Ok(time::join_time(*secs, *nanos)),
+            _ => Err(invalid_type("SystemTime", v)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for ObjectMap {
+    type Error = ConversionError;
+
+    fn try_from(v: &Value) -> Result<ObjectMap, ConversionError> {
+        v.as_object()
+            .cloned()
+            .ok_or_else(|| invalid_type("ObjectMap", v))
+    }
+}
+
+macro_rules! try_from_by_ref {
+    ($($type: ty), *) => {
+        $(impl TryFrom<Value> for $type {
+            type Error = ConversionError;
+
+            fn try_from(v: Value) -> Result<$type, ConversionError> {
+                <$type>::try_from(&v)
+            }
+        })*
+    }
+}
+
+try_from_by_ref! {bool, char, SystemTime}
+
+impl TryFrom<Value> for String {
+    type Error = ConversionError;
+
+    fn try_from(v: Value) -> Result<String, ConversionError> {
+        match v {
+            Value::String(s) => Ok(s),
+            v => Err(invalid_type("String", &v)),
+        }
+    }
+}
+
+impl TryFrom<Value> for Bytes {
+    type Error = ConversionError;
+
+    fn try_from(v: Value) -> Result<Bytes, ConversionError> {
+        match v {
+            Value::Binary(b) => Ok(Bytes(b)),
+            Value::String(s) => Ok(Bytes(s.into_bytes())),
+            v => Err(invalid_type("Bytes", &v)),
+        }
+    }
+}
+
+impl TryFrom<Value> for ObjectMap {
+    type Error = ConversionError;
+
+    fn try_from(v: Value) -> Result<ObjectMap, ConversionError> {
+        match v {
+            Value::Object(o) => Ok(o),
+            v => Err(invalid_type("ObjectMap", &v)),
+        }
+    }
+}
+
+impl<'a, T> TryFrom<&'a Value> for Vec<T>
+where
+    T: TryFrom<&'a Value, Error = ConversionError> + TryFrom<Value, Error = ConversionError>,
+{
+    type Error = ConversionError;
+
+    fn try_from(v: &'a Value) -> Result<Vec<T>, ConversionError> {
+        match v {
+            Value::Array(arr) => arr.iter().map(T::try_from).collect(),
+            Value::Binary(bytes) => bytes
+                .iter()
+                .map(|b| T::try_from(Value::UInt(u64::from(*b))))
+                .collect(),
+            _ => Err(invalid_type("Array", v)),
+        }
+    }
+}
+
+impl<T> TryFrom<Value> for Vec<T>
+where
+    T: TryFrom<Value, Error = ConversionError>,
+{
+    type Error = ConversionError;
+
+    fn try_from(v: Value) -> Result<Vec<T>, ConversionError> {
+        match v {
+            Value::Array(arr) => arr.into_iter().map(T::try_from).collect(),
+            Value::Binary(bytes) => bytes
+                .into_iter()
+                .map(|b| T::try_from(Value::UInt(u64::from(b))))
+                .collect(),
+            v => Err(invalid_type("Array", &v)),
+        }
+    }
+}
+
+impl<'a, T, S> TryFrom<&'a Value> for HashMap<String, T, S>
+where
+    T: TryFrom<&'a Value, Error = ConversionError>,
+    S: BuildHasher + Default,
+{
+    type Error = ConversionError;
+
+    fn try_from(v: &'a Value) -> Result<HashMap<String, T, S>, ConversionError> {
+        match v {
+            Value::Object(o) => o
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), T::try_from(v)?)))
+                .collect(),
+            _ => Err(invalid_type("HashMap", v)),
+        }
+    }
+}
+
+impl<T, S> TryFrom<Value> for HashMap<String, T, S>
+where
+    T: TryFrom<Value, Error = ConversionError>,
+    S: BuildHasher + Default,
+{
+    type Error = ConversionError;
+
+    fn try_from(v: Value) -> Result<HashMap<String, T, S>, ConversionError> {
+        match v {
+            Value::Object(o) => o
+                .into_iter()
+                .map(|(k, v)| Ok((k, T::try_from(v)?)))
+                .collect(),
+            v => Err(invalid_type("HashMap", &v)),
+        }
+    }
+}
+
+impl<'a, T> TryFrom<&'a Value> for BTreeMap<String, T>
+where
+    T: TryFrom<&'a Value, Error = ConversionError>,
+{
+    type Error = ConversionError;
+
+    fn try_from(v: &'a Value) -> Result<BTreeMap<String, T>, ConversionError> {
+        match v {
+            Value::Object(o) => o
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), T::try_from(v)?)))
+                .collect(),
+            _ => Err(invalid_type("BTreeMap", v)),
+        }
+    }
+}
+
+impl<T> TryFrom<Value> for BTreeMap<String, T>
+where
+    T: TryFrom<Value, Error = ConversionError>,
+{
+    type Error = ConversionError;
+
+    fn try_from(v: Value) -> Result<BTreeMap<String, T>, ConversionError> {
+        match v {
+            Value::Object(o) => o
+                .into_iter()
+                .map(|(k, v)| Ok((k, T::try_from(v)?)))
+                .collect(),
+            v => Err(invalid_type("BTreeMap", &v)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total<V: Into<Value>>(items: Vec<V>) -> Result<i64, ConversionError> {
+        let v: Value = items.into();
+        let numbers = Vec::<i64>::try_from(&v)?;
+        Ok(numbers.iter().sum())
+    }
+
+    #[test]
+    fn test_into_value() {
+        assert_eq!(Value::from(1u8), Value::UInt(1));
+        assert_eq!(Value::from(-1i32), Value::Int(-1));
+        assert_eq!(Value::from(0.5f32), Value::F32(0.5));
+        assert_eq!(Value::from('x'), Value::String("x".to_string()));
+        assert_eq!(Value::from("x"), Value::String("x".to_string()));
+        assert_eq!(Value::from(Bytes(vec![1])), Value::Binary(vec![1]));
+        assert_eq!(Value::from(vec!["a", "b"]), value!(["a", "b"]));
+
+        let mut m = HashMap::new();
+        m.insert("a", vec![1]);
+        assert_eq!(Value::from(m), value!({"a": [1]}));
+        let mut m = BTreeMap::new();
+        m.insert("b".to_string(), true);
+        assert_eq!(Value::from(m), value!({"b": true}));
+
+        assert_eq!(total(vec![1, 2, 3]), Ok(6));
+        assert!(total(vec!["a"]).is_err());
+    }
+
+    #[test]
+    fn test_try_from_value() {
+        assert_eq!(u8::try_from(&value!(255)), Ok(255));
+        assert_eq!(i64::try_from(Value::F64(3.0)), Ok(3));
+        assert_eq!(f64::try_from(value!(2)), Ok(2.0));
+        assert_eq!(bool::try_from(value!(true)), Ok(true));
+        assert_eq!(char::try_from(value!("é")), Ok('é'));
+        assert_eq!(String::try_from(value!("x")), Ok("x".to_string()));
+        assert_eq!(
+            Vec::<u8>::try_from(Value::Binary(vec![1, 2])),
+            Ok(vec![1, 2])
+        );
+        assert_eq!(Bytes::try_from(&Value::Binary(vec![1])), Ok(Bytes(vec![1])));
+
+        let v = value!({"a": [1, 2], "b": []});
+        let m: HashMap<String, Vec<u32>> = HashMap::try_from(&v).unwrap();
+        assert_eq!(m["a"], vec![1, 2]);
+        let m: BTreeMap<String, Vec<u32>> = BTreeMap::try_from(v).unwrap();
+        assert_eq!(m.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_conversion_errors() {
+        assert_eq!(
+            u8::try_from(&value!(256)),
+            Err(ConversionError::OutOfRange(
+                "256 does not fit in u8".to_string()
+            ))
+        );
+        assert!(matches!(
+            u32::try_from(value!(-1)),
+            Err(ConversionError::OutOfRange(_))
+        ));
+        assert!(matches!(
+            i32::try_from(Value::F64(0.5)),
+            Err(ConversionError::OutOfRange(_))
+        ));
+        assert_eq!(
+            bool::try_from(value!("true")).unwrap_err().to_string(),
+            "InvalidType: expected bool, found String"
+        );
+        assert!(char::try_from(value!("ab")).is_err());
+        assert!(Vec::<i32>::try_from(value!([1, "x"])).is_err());
+        assert!(HashMap::<String, i32>::try_from(value!([1])).is_err());
+    }
+}
@@ -0,0 +1,409 @@
+use crate::object_map::{Entry, ObjectMap};
+use crate::pointer::{map_key_position, parse_array_index, parse_pointer};
+use crate::value::Value;
+use std::error::Error as StdError;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    InvalidPointer(String),
+    NotAContainer(String),
+    IndexOutOfRange(String),
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathError::InvalidPointer(p) => write!(f, "InvalidPointer: {}", p),
+            PathError::NotAContainer(p) => write!(f, "NotAContainer: {}", p),
+            PathError::IndexOutOfRange(p) => write!(f, "IndexOutOfRange: {}", p),
+        }
+    }
+}
+
+impl StdError for PathError {}
+
+/// Something that can address a child of a `Value`: a `&str` key for
+/// objects and for map entries with string keys, a `usize` position for
+/// arrays.
+pub trait ValueIndex {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value>;
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value>;
+}
+
+impl ValueIndex for str {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::Object(o) => o.get(self),
+            Value::Map(pairs) => Some(&pairs[map_key_position(pairs, self)?].1),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        match v {
+            Value::Object(o) => o.get_mut(self),
+            Value::Map(pairs) => {
+                let i = map_key_position(pairs, self)?;
+                Some(&mut pairs[i].1)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl ValueIndex for String {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        self.as_str().index_into(v)
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        self.as_str().index_into_mut(v)
+    }
+}
+
+impl ValueIndex for usize {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::Array(arr) => arr.get(*self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        match v {
+            Value::Array(arr) => arr.get_mut(*self),
+            _ => None,
+        }
+    }
+}
+
+impl<T> ValueIndex for &T
+where
+    T: ValueIndex + ?Sized,
+{
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(v)
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        (**self).index_into_mut(v)
+    }
+}
+
+impl Value {
+    pub fn get<I: ValueIndex>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    pub fn get_mut<I: ValueIndex>(&mut self, index: I) -> Option<&mut Value> {
+        index.index_into_mut(self)
+    }
+
+    /// Entry for `key` in an object. `Null` is turned into an empty object
+    /// first; any other non-object value gives `None`.
+    pub fn entry(&mut self, key: &str) -> Option<Entry<'_>> {
+        if let Value::Null = self {
+            *self = Value::Object(ObjectMap::new());
+        }
+        match self {
+            Value::Object(o) => Some(o.entry(key.to_string())),
+            _ => None,
+        }
+    }
+
+    /// Replaces this value with `Null` and returns the previous one.
+    pub fn take(&mut self) -> Value {
+        std::mem::take(self)
+    }
+
+    /// Appends to an array. `Null` is turned into an empty array first.
+    pub fn push(&mut self, v: Value) -> Result<(), PathError> {
+        if let Value::Null = self {
+            *self = Value::Array(Vec::new());
+        }
+        match self {
+            Value::Array(arr) => {
+                arr.push(v);
+                Ok(())
+            }
+            _ => Err(PathError::NotAContainer(String::new())),
+        }
+    }
+
+    /// Sets the value at `pointer`, creating missing objects and arrays on
+    /// the way. A missing container becomes an array when the next token is
+    /// an index or `-`, otherwise an object. `-` and the index equal to the
+    /// length append to an array. In a map, tokens address entries with
+    /// string keys and missing ones are appended. Returns the value that was
+    /// replaced.
+    pub fn insert_at_path(&mut self, pointer: &str, v: Value) -> Result<Option<Value>, PathError> {
+        let tokens =
+            parse_pointer(pointer).ok_or_else(|| PathError::InvalidPointer(pointer.to_string()))?;
+        let (last, parents) = match tokens.split_last() {
+            Some(split) => split,
+            None => return Ok(Some(std::mem::replace(self, v))),
+        };
+
+        let mut target = self;
+        for (depth, token) in parents.iter().enumerate() {
+            let next_is_index = is_index_token(&tokens[depth + 1]);
+            let path = join_tokens(&tokens[..=depth]);
+            target = child_or_create(target, token, next_is_index, &path)?;
+        }
+
+        let path = pointer.to_string();
+        if let Value::Null = target {
+            *target = if is_index_token(last) {
+                Value::Array(Vec::new())
+            } else {
+                Value::Object(ObjectMap::new())
+            };
+        }
+        match target {
+            Value::Object(o) => Ok(o.insert(last.clone(), v)),
+            Value::Map(pairs) => match map_key_position(pairs, last) {
+                Some(i) => Ok(Some(std::mem::replace(&mut pairs[i].1, v))),
+                None => {
+                    pairs.push((Value::String(last.clone()), v));
+                    Ok(None)
+                }
+            },
+            Value::Array(arr) => {
+                let i = array_position(arr, last, &path)?;
+                if i == arr.len() {
+                    arr.push(v);
+                    Ok(None)
+                } else {
+                    Ok(Some(std::mem::replace(&mut arr[i], v)))
+                }
+            }
+            _ => Err(PathError::NotAContainer(join_tokens(parents))),
+        }
+    }
+
+    /// Removes and returns the value at `pointer`. Object entries keep the
+    /// order of their siblings, array elements after it shift down.
+    pub fn remove_at_path(&mut self, pointer: &str) -> Option<Value> {
+        let tokens = parse_pointer(pointer)?;
+        let (last, parents) = match tokens.split_last() {
+            Some(split) => split,
+            None => return Some(self.take()),
+        };
+
+        let mut target = self;
+        for token in parents {
+            target = target.child_mut(token)?;
+        }
+        match target {
+            Value::Object(o) => o.remove(last),
+            Value::Map(pairs) => {
+                let i = map_key_position(pairs, last)?;
+                Some(pairs.remove(i).1)
+            }
+            Value::Array(arr) => {
+                let i = parse_array_index(last)?;
+                if i < arr.len() {
+                    Some(arr.remove(i))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn is_index_token(token: &str) -> bool {
+    token == "-" || parse_array_index(token).is_some()
+}
+
+fn join_tokens(tokens: &[String]) -> String {
+    let mut result = String::new();
+    for t in tokens {
+        result += "/";
+        result += &crate::pointer::escape_pointer_token(t);
+    }
+    result
+}
+
+fn array_position(arr: &[Value], token: &str, path: &str) -> Result<usize, PathError> {
+    if token == "-" {
+        return Ok(arr.len());
+    }
+    match parse_array_index(token) {
+        Some(i) if i <= arr.len() => Ok(i),
+        Some(_) => Err(PathError::IndexOutOfRange(path.to_string())),
+        None => Err(PathError::InvalidPointer(path.to_string())),
+    }
+}
+
+fn child_or_create<'v>(
+    target: &'v mut Value,
+    token: &str,
+    next_is_index: bool,
+    path: &str,
+) -> Result<&'v mut Value, PathError> {
+    let empty = || {
+        if next_is_index {
+            Value::Array(Vec::new())
+        } else {
+            Value::Object(ObjectMap::new())
+        }
+    };
+    if let Value::Null = target {
+        *target = if is_index_token(token) {
+            Value::Array(Vec::new())
+        } else {
+            Value::Object(ObjectMap::new())
+        };
+    }
+    match target {
+        Value::Object(o) => {
+            let child = o.entry(token.to_string()).or_insert_with(empty);
+            if let Value::Null = child {
+                *child = empty();
+            }
+            Ok(child)
+        }
+        Value::Map(pairs) => {
+            let i = match map_key_position(pairs, token) {
+                Some(i) => i,
+                None => {
+                    pairs.push((Value::String(token.to_string()), empty()));
+                    pairs.len() - 1
+                }
+            };
+            let child = &mut pairs[i].1;
+            if let Value::Null = child {
+                *child = empty();
+            }
+            Ok(child)
+        }
+        Value::Array(arr) => {
+            let i = array_position(arr, token, path)?;
+            if i == arr.len() {
+                arr.push(empty());
+            }
+            let child = &mut arr[i];
+            if let Value::Null = child {
+                *child = empty();
+            }
+            Ok(child)
+        }
+        _ => Err(PathError::NotAContainer(path.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_mut_and_take() {
+        let mut v = Value::Array(vec![Value::Int(1), Value::Int(2)]);
+        *v.get_mut(1).unwrap() = Value::Bool(true);
+        assert!(matches!(v.get(1), Some(Value::Bool(true))));
+        assert!(v.get("a").is_none());
+
+        let taken = v.get_mut(0).unwrap().take();
+        assert!(matches!(taken, Value::Int(1)));
+        assert!(matches!(v[0], Value::Null));
+
+        v.push(Value::Int(3)).unwrap();
+        assert!(matches!(v[2], Value::Int(3)));
+        assert!(Value::Bool(true).push(Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut v = Value::Null;
+        v.entry("count").unwrap().or_insert(Value::Int(7));
+        assert!(matches!(v["count"], Value::Int(7)));
+        assert!(Value::Int(1).entry("x").is_none());
+    }
+
+    #[test]
+    fn test_insert_at_path() {
+        let mut v = Value::Null;
+        v.insert_at_path("/a/b/0/c", Value::Int(1)).unwrap();
+        assert!(matches!(v.pointer("/a/b/0/c"), Some(Value::Int(1))));
+        assert!(matches!(v["a"]["b"], Value::Array(_)));
+
+        v.insert_at_path("/a/b/-", Value::Int(2)).unwrap();
+
+        let mut list = Value::Null;
+        list.insert_at_path("/0/name", Value::Bool(true)).unwrap();
+        assert!(matches!(list.pointer("/0/name"), Some(Value::Bool(true))));
+        assert!(matches!(v.pointer("/a/b/1"), Some(Value::Int(2))));
+
+        let old = v.insert_at_path("/a/b/1", Value::Int(3)).unwrap();
+        assert!(matches!(old, Some(Value::Int(2))));
+
+        assert_eq!(
+            v.insert_at_path("/a/b/5", Value::Null).unwrap_err(),
+            PathError::IndexOutOfRange("/a/b/5".to_string())
+        );
+        assert_eq!(
+            v.insert_at_path("/a/b/0/c/d", Value::Null).unwrap_err(),
+            PathError::NotAContainer("/a/b/0/c".to_string())
+        );
+        assert!(v.insert_at_path("a", Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_remove_at_path() {
+        let mut v = Value::Null;
+        v.insert_at_path("/x", Value::Int(1)).unwrap();
+        v.insert_at_path("/y/0", Value::Int(2)).unwrap();
+        v.insert_at_path("/y/1", Value::Int(3)).unwrap();
+        v.insert_at_path("/z", Value::Int(4)).unwrap();
+
+        assert!(matches!(v.remove_at_path("/y/0"), Some(Value::Int(2))));
+        assert!(matches!(v.pointer("/y/0"), Some(Value::Int(3))));
+        assert!(matches!(v.remove_at_path("/x"), Some(Value::Int(1))));
+        assert!(v.remove_at_path("/x").is_none());
+        assert!(v.remove_at_path("/y/9").is_none());
+
+        match &v {
+            Value::Object(o) => {
+                let keys: Vec<&str> = o.keys().map(|k| k.as_str()).collect();
+                assert_eq!(keys, vec!["y", "z"]);
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_map_paths() {
+        let key = |k: &str| Value::String(k.to_string());
+        let mut v = Value::Map(vec![
+            (Value::Int(1), key("one")),
+            (key("a"), Value::Map(vec![(key("b"), Value::Int(2))])),
+        ]);
+
+        assert_eq!(v.get("a").and_then(|a| a.get("b")), Some(&Value::Int(2)));
+        *v.get_mut("a").unwrap().get_mut("b").unwrap() = Value::Int(3);
+        assert_eq!(v.pointer("/a/b"), Some(&Value::Int(3)));
+
+        let old = v.insert_at_path("/a/b", Value::Int(4)).unwrap();
+        assert_eq!(old, Some(Value::Int(3)));
+        assert_eq!(v["a"]["b"], Value::Int(4));
+        v.insert_at_path("/a/c/0", Value::Bool(true)).unwrap();
+        assert_eq!(v.pointer("/a/c/0"), Some(&Value::Bool(true)));
+        v.insert_at_path("/d", Value::Null).unwrap();
+        assert_eq!(v.pointer("/d"), Some(&Value::Null));
+
+        assert_eq!(v.remove_at_path("/a/b"), Some(Value::Int(4)));
+        assert_eq!(v.pointer("/a/b"), None);
+        assert_eq!(v.remove_at_path("/d"), Some(Value::Null));
+        assert_eq!(v.remove_at_path("/d"), None);
+        match &v {
+            Value::Map(pairs) => {
+                assert_eq!(pairs.len(), 2);
+                assert_eq!(pairs[0], (Value::Int(1), key("one")));
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+}
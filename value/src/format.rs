@@ -0,0 +1,339 @@
+//! Rendering `Value` as JSON text.
+//!
+//! `{}` renders compact JSON and `{:#}` pretty JSON indented by four spaces.
+//! `Formatter` exposes the other options.
+//!
+//! Variants JSON has no equivalent for are rendered as:
+//! - `Binary` as a string of lowercase hex digits, or base64.
+//! - `Ext` as `{"ext": type_id, "data": <binary>}`.
+//! - `Timestamp` as an RFC 3339 string.
+//! - `Map` with each key turned into a string: binary and timestamp keys as
+//!   above, any other non-string key rendered compactly.
+//! - NaN and infinite floats as `null`.
+
+use crate::object_map::ObjectMap;
+use crate::time;
+use crate::value::Value;
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    Hex,
+    Base64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formatter {
+    indent: Option<usize>,
+    sort_keys: bool,
+    binary: BinaryFormat,
+}
+
+impl Default for Formatter {
+    fn default() -> Formatter {
+        Formatter::compact()
+    }
+}
+
+impl Formatter {
+    /// Everything on one line, no spaces.
+    pub fn compact() -> Formatter {
+        Formatter {
+            indent: None,
+            sort_keys: false,
+            binary: BinaryFormat::Hex,
+        }
+    }
+
+    /// One array element or object member per line, indented by four spaces.
+    pub fn pretty() -> Formatter {
+        Formatter::compact().indent(4)
+    }
+
+    /// Switches to pretty mode, indenting each level by `width` spaces.
+    pub fn indent(mut self, width: usize) -> Formatter {
+        self.indent = Some(width);
+        self
+    }
+
+    /// Writes object and map members sorted by key instead of in insertion
+    /// order. Map keys are sorted as the strings they render to, so `"10"`
+    /// comes before `2`.
+    pub fn sort_keys(mut self, sort: bool) -> Formatter {
+        self.sort_keys = sort;
+        self
+    }
+
+    pub fn binary_format(mut self, binary: BinaryFormat) -> Formatter {
+        self.binary = binary;
+        self
+    }
+
+    pub fn format(&self, v: &Value) -> String {
+        let mut result = String::new();
+        // Writing to a String cannot fail.
+        let _ = self.write_to(v, &mut result);
+        result
+    }
+
+    pub fn write_to<W: Write>(&self, v: &Value, out: &mut W) -> fmt::Result {
+        self.write_value(v, 0, out)
+    }
+
+    fn write_value<W: Write>(&self, v: &Value, level: usize, out: &mut W) -> fmt::Result {
+        match v {
+            Value::Null => out.write_str("null"),
+            Value::Bool(b) => write!(out, "{}", b),
+            Value::Int(n) => write!(out, "{}", n),
+            Value::UInt(n) => write!(out, "{}", n),
+            Value::F32(n) if n.is_finite() => write!(out, "{:?}", n),
+            Value::F64(n) if n.is_finite() => write!(out, "{:?}", n),
+            Value::F32(_) | Value::F64(_) => out.write_str("null"),
+            Value::String(s) => write_escaped(s, out),
+            Value::Binary(b) => write_escaped(&self.encode_binary(b), out),
+            Value::Ext { type_id, data } => {
+                let mut ext = ObjectMap::new();
+                ext.insert("ext".to_string(), Value::Int(i64::from(*type_id)));
+                ext.insert("data".to_string(), Value::Binary(data.clone()));
+                self.write_members(ext.iter().collect(), level, out)
+            }
+            Value::Timestamp { secs, nanos } => {
+                write_escaped(&time::format_rfc3339(*secs, *nanos), out)
+            }
+            Value::Array(arr) => self.write_array(arr, level, out),
+            Value::Object(o) => {
+                let mut members: Vec<(&String, &Value)> = o.iter().collect();
+                if self.sort_keys {
+                    members.sort_by(|a, b| a.0.cmp(b.0));
+                }
+                self.write_members(members, level, out)
+            }
+            Value::Map(pairs) => {
+                let keys: Vec<String> = pairs.iter().map(|(k, _)| self.key_string(k)).collect();
+                let mut members: Vec<(&String, &Value)> =
+                    keys.iter().zip(pairs.iter().map(|(_, v)| v)).collect();
+                if self.sort_keys {
+                    members.sort_by(|a, b| a.0.cmp(b.0));
+                }
+                self.write_members(members, level, out)
+            }
+        }
+    }
+
+    fn write_array<W: Write>(&self, arr: &[Value], level: usize, out: &mut W) -> fmt::Result {
+        if arr.is_empty() {
+            return out.write_str("[]");
+        }
+        out.write_char('[')?;
+        for (idx, item) in arr.iter().enumerate() {
+            if idx > 0 {
+                out.write_char(',')?;
+            }
+            self.write_newline(level + 1, out)?;
+            self.write_value(item, level + 1, out)?;
+        }
+        self.write_newline(level, out)?;
+        out.write_char(']')
+    }
+
+    fn write_members<W: Write>(
+        &self,
+        members: Vec<(&String, &Value)>,
+        level: usize,
+        out: &mut W,
+    ) -> fmt::Result {
+        if members.is_empty() {
+            return out.write_str("{}");
+        }
+        out.write_char('{')?;
+        for (idx, (k, v)) in members.into_iter().enumerate() {
+            if idx > 0 {
+                out.write_char(',')?;
+            }
+            self.write_newline(level + 1, out)?;
+            write_escaped(k, out)?;
+            out.write_char(':')?;
+            if self.indent.is_some() {
+                out.write_char(' ')?;
+            }
+            self.write_value(v, level + 1, out)?;
+        }
+        self.write_newline(level, out)?;
+        out.write_char('}')
+    }
+
+    fn write_newline<W: Write>(&self, level: usize, out: &mut W) -> fmt::Result {
+        match self.indent {
+            Some(width) => {
+                out.write_char('\n')?;
+                for _ in 0..width * level {
+                    out.write_char(' ')?;
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn key_string(&self, key: &Value) -> String {
+        match key {
+            Value::String(s) => s.clone(),
+            Value::Binary(b) => self.encode_binary(b),
+            Value::Timestamp { secs, nanos } => time::format_rfc3339(*secs, *nanos),
+            _ => Formatter {
+                indent: None,
+                ..self.clone()
+            }
+            .format(key),
+        }
+    }
+
+    fn encode_binary(&self, data: &[u8]) -> String {
+        match self.binary {
+            BinaryFormat::Hex => {
+                let mut result = String::with_capacity(data.len() * 2);
+                for b in data {
+                    let _ = write!(result, "{:02x}", b);
+                }
+                result
+            }
+            BinaryFormat::Base64 => encode_base64(data),
+        }
+    }
+}
+
+/// Writes `s` as a quoted JSON string.
+pub(crate) fn write_escaped<W: Write + ?Sized>(s: &str, out: &mut W) -> fmt::Result {
+    out.write_char('"')?;
+    write_escaped_chars(s, out)?;
+    out.write_char('"')
+}
+
+/// Writes the characters of `s` escaped for a JSON string, without quotes.
+pub(crate) fn write_escaped_chars<W: Write + ?Sized>(s: &str, out: &mut W) -> fmt::Result {
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            '\u{8}' => out.write_str("\\b")?,
+            '\u{c}' => out.write_str("\\f")?,
+            c if (c as u32) < 0x20 || c == '\u{7f}' => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            Formatter::pretty().write_to(self, f)
+        } else {
+            Formatter::compact().write_to(self, f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_and_pretty() {
+        let v = value!({"b": [1, 2.5, null], "a": {}, "c": []});
+        assert_eq!(v.to_string(), r#"{"b":[1,2.5,null],"a":{},"c":[]}"#);
+        assert_eq!(
+            format!("{:#}", v),
+            "{\n    \"b\": [\n        1,\n        2.5,\n        null\n    ],\n    \"a\": {},\n    \"c\": []\n}"
+        );
+        assert_eq!(
+            Formatter::compact().indent(1).sort_keys(true).format(&v),
+            "{\n \"a\": {},\n \"b\": [\n  1,\n  2.5,\n  null\n ],\n \"c\": []\n}"
+        );
+    }
+
+    #[test]
+    fn test_escaping() {
+        let v = value!("a\"b\\c\nd\te\u{1}");
+        assert_eq!(v.to_string(), r#""a\"b\\c\nd\te\u0001""#);
+        assert_eq!(value!("é☃").to_string(), "\"é☃\"");
+        assert_eq!(value!({"k\n": 1}).to_string(), r#"{"k\n":1}"#);
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(Value::F64(1.0).to_string(), "1.0");
+        assert_eq!(Value::F32(0.1).to_string(), "0.1");
+        assert_eq!(Value::F64(1e300).to_string(), "1e300");
+        assert_eq!(Value::F64(f64::NAN).to_string(), "null");
+        assert_eq!(Value::UInt(u64::MAX).to_string(), "18446744073709551615");
+    }
+
+    #[test]
+    fn test_non_json_variants() {
+        let bin = Value::Binary(vec![0xde, 0xad, 0xbe, 0xef, 0x01]);
+        assert_eq!(bin.to_string(), r#""deadbeef01""#);
+        assert_eq!(
+            Formatter::compact()
+                .binary_format(BinaryFormat::Base64)
+                .format(&bin),
+            r#""3q2+7wE=""#
+        );
+        assert_eq!(encode_base64(b"ab"), "YWI=");
+        assert_eq!(encode_base64(b"abc"), "YWJj");
+
+        let ext = Value::Ext {
+            type_id: 5,
+            data: vec![1, 2],
+        };
+        assert_eq!(ext.to_string(), r#"{"ext":5,"data":"0102"}"#);
+
+        let ts = Value::Timestamp { secs: 0, nanos: 0 };
+        assert_eq!(ts.to_string(), r#""1970-01-01T00:00:00Z""#);
+
+        let map = Value::Map(vec![
+            (Value::Int(2), Value::Bool(true)),
+            (Value::Array(vec![Value::Int(1)]), Value::Null),
+            (Value::String("s".to_string()), Value::Int(0)),
+        ]);
+        assert_eq!(map.to_string(), r#"{"2":true,"[1]":null,"s":0}"#);
+        assert_eq!(
+            Formatter::compact().sort_keys(true).format(&map),
+            r#"{"2":true,"[1]":null,"s":0}"#
+        );
+
+        let mixed = Value::Map(vec![
+            (Value::Int(2), Value::Int(0)),
+            (Value::String("10".to_string()), Value::Int(1)),
+            (Value::F64(1.5), Value::Int(2)),
+        ]);
+        assert_eq!(
+            Formatter::compact().sort_keys(true).format(&mixed),
+            r#"{"1.5":2,"10":1,"2":0}"#
+        );
+    }
+}
@@ -1,6 +1,13 @@
+use crate::object_map::ObjectMap;
 use crate::value::*;
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::time::SystemTime;
 
+/// Conversion out of a `Value` that panics when the value does not fit.
+/// `TryFrom<&Value>` does the same conversions and returns a
+/// `ConversionError` instead.
+#[allow(clippy::wrong_self_convention)]
 pub trait FromValue<T>: Default {
     fn from_value(&self) -> T;
 }
@@ -11,22 +18,19 @@ impl FromValue<Value> for Value {
     }
 }
 
-impl FromValue<bool> for Value {
-    fn from_value(&self) -> bool {
-        match self {
-            Value::Bool(n) => *n,
-            _ => panic!("invalid value for bool"),
-        }
+macro_rules! from_value_via_try_from {
+    ($($type: ty), *) => {
+        $(impl FromValue<$type> for Value {
+            fn from_value(&self) -> $type {
+                <$type>::try_from(self).unwrap_or_else(|e| panic!("{}", e))
+            }
+        })*
     }
 }
 
-impl FromValue<String> for Value {
-    fn from_value(&self) -> String {
-        match self {
-            Value::String(s) => s.clone(),
-            _ => panic!("invalid value for String"),
-        }
-    }
+from_value_via_try_from! {
+    bool, String, Bytes, SystemTime, ObjectMap, HashMap<String, String>,
+    i8, u8, i16, u16, i32, u32, i64, u64, f32, f64
 }
 
 impl<T> FromValue<Vec<T>> for Value
@@ -36,54 +40,33 @@ where
     fn from_value(&self) -> Vec<T> {
         match self {
             Value::Array(array) => array.iter().map(|x| x.from_value()).collect(),
-            _ => panic!("invalid value for Array"),
-        }
-    }
-}
-
-impl FromValue<HashMap<String, Value>> for Value {
-    fn from_value(&self) -> HashMap<String, Value> {
-        match self {
-            Value::Object(hm) => hm.clone(),
-            _ => panic!("invalid value for HashMap"),
+            Value::Binary(bytes) => bytes
+                .iter()
+                .map(|b| Value::UInt(u64::from(*b)).from_value())
+                .collect(),
+            _ => panic!("InvalidType: expected Array, found {}", self.kind()),
         }
     }
 }
 
-impl FromValue<HashMap<String, String>> for Value {
-    fn from_value(&self) -> HashMap<String, String> {
+/// `Null` reads as `None`.
+impl<T> FromValue<Option<T>> for Value
+where
+    Value: FromValue<T>,
+{
+    fn from_value(&self) -> Option<T> {
         match self {
-            Value::Object(hm) => {
-                let mut result: HashMap<String, String> = HashMap::new();
-                for key in hm.keys() {
-                    let value = hm.get(key).unwrap();
-                    result.insert(key.clone(), value.from_value());
-                }
-                result
-            }
-            _ => panic!("invalid value for HashMap"),
+            Value::Null => None,
+            v => Some(v.from_value()),
         }
     }
 }
 
-macro_rules! mark_trait {
-    ($trait: ident; $($type: ty), *) => {
-        $(impl $trait for $type {})*
-    }
-}
-
-pub trait ToNumber {}
-mark_trait!{ToNumber; i8, u8, i16, u16, i32, u32, i64, u64, f32, f64}
-
-impl<T> FromValue<T> for Value
-where
-    T: std::str::FromStr + ToNumber,
-    <T as std::str::FromStr>::Err: std::fmt::Debug,
-{
-    fn from_value(&self) -> T {
+impl FromValue<HashMap<String, Value>> for Value {
+    fn from_value(&self) -> HashMap<String, Value> {
         match self {
-            Value::Number(n) => n.parse().unwrap(),
-            _ => panic!("invalid value"),
+            Value::Object(o) => o.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            _ => panic!("InvalidType: expected HashMap, found {}", self.kind()),
         }
     }
 }
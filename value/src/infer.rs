@@ -0,0 +1,578 @@
+//! Inferring a schema from sample values, and Rust types from the schema.
+//!
+//! Feed `Inference` every decoded message of an undocumented stream, then
+//! read the result as a schema document for `Schema::compile` or as Rust
+//! structs that `value_derive` can read and write:
+//!
+//! - A member missing from some samples, or `null` in some, is optional:
+//!   left out of `required` and typed `Option<T>`.
+//! - A position that held several kinds of values gets a `type` array, and
+//!   `Value` in Rust unless the kinds are only integers and floats.
+//! - Integers become `i64`, or `u64` when a sample exceeds `i64`. Integers
+//!   mixed with floats are a `number` and become `f64`.
+//! - Timestamps become `Option<SystemTime>` since `SystemTime` has no
+//!   `Default`; maps and extension values stay `Value`.
+
+use crate::object_map::ObjectMap;
+use crate::value::Value;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Default)]
+pub struct Inference {
+    root: Shape,
+}
+
+// Everything seen at one position of the samples.
+#[derive(Debug, Clone, Default)]
+struct Shape {
+    // Samples that had a value here, null included.
+    seen: usize,
+    null: bool,
+    boolean: bool,
+    // Smallest and largest integer seen.
+    integers: Option<(i128, i128)>,
+    float: bool,
+    string: bool,
+    binary: bool,
+    ext: bool,
+    timestamp: bool,
+    map: bool,
+    // Shape of the elements, once an array has been seen.
+    items: Option<Box<Shape>>,
+    object: Option<Fields>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Fields {
+    // Objects seen here; a field seen fewer times is optional.
+    count: usize,
+    fields: Vec<(String, Shape)>,
+}
+
+impl Shape {
+    fn add(&mut self, v: &Value) {
+        self.seen += 1;
+        match v {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.boolean = true,
+            Value::Int(n) => self.add_integer(*n as i128),
+            Value::UInt(n) => self.add_integer(*n as i128),
+            Value::F32(_) | Value::F64(_) => self.float = true,
+            Value::String(_) => self.string = true,
+            Value::Binary(_) => self.binary = true,
+            Value::Ext { .. } => self.ext = true,
+            Value::Timestamp { .. } => self.timestamp = true,
+            Value::Map(_) => self.map = true,
+            Value::Array(arr) => {
+                let items = self.items.get_or_insert_with(Box::default);
+                for item in arr {
+                    items.add(item);
+                }
+            }
+            Value::Object(o) => {
+                let object = self.object.get_or_insert_with(Fields::default);
+                object.count += 1;
+                for (k, v) in o {
+                    match object.fields.iter_mut().find(|(name, _)| name == k) {
+                        Some((_, shape)) => shape.add(v),
+                        None => {
+                            let mut shape = Shape::default();
+                            shape.add(v);
+                            object.fields.push((k.clone(), shape));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_integer(&mut self, n: i128) {
+        self.integers = Some(match self.integers {
+            Some((min, max)) => (min.min(n), max.max(n)),
+            None => (n, n),
+        });
+    }
+
+    // The schema type names, in a fixed order.
+    fn type_names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        let flags = [
+            (self.null, "null"),
+            (self.boolean, "boolean"),
+            (self.integers.is_some() && !self.float, "integer"),
+            (self.float, "number"),
+            (self.string, "string"),
+            (self.binary, "binary"),
+            (self.ext, "ext"),
+            (self.timestamp, "timestamp"),
+            (self.items.is_some(), "array"),
+            (self.object.is_some(), "object"),
+            (self.map, "map"),
+        ];
+        for (present, name) in flags.iter() {
+            if *present {
+                names.push(*name);
+            }
+        }
+        names
+    }
+
+    fn to_schema(&self) -> Value {
+        let names = self.type_names();
+        if names.is_empty() {
+            return Value::Bool(true);
+        }
+        let mut schema = ObjectMap::new();
+        let types = match names.as_slice() {
+            [name] => Value::String(name.to_string()),
+            names => Value::Array(names.iter().map(|n| Value::String(n.to_string())).collect()),
+        };
+        schema.insert("type".to_string(), types);
+        if let Some(items) = &self.items {
+            if items.seen > 0 {
+                schema.insert("items".to_string(), items.to_schema());
+            }
+        }
+        if let Some(object) = &self.object {
+            let mut properties = ObjectMap::new();
+            let mut required = Vec::new();
+            for (name, shape) in &object.fields {
+                properties.insert(name.clone(), shape.to_schema());
+                if shape.seen == object.count {
+                    required.push(Value::String(name.clone()));
+                }
+            }
+            schema.insert("properties".to_string(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".to_string(), Value::Array(required));
+            }
+        }
+        Value::Object(schema)
+    }
+}
+
+impl Inference {
+    pub fn new() -> Inference {
+        Inference::default()
+    }
+
+    /// Merges one sample into the schema.
+    pub fn add(&mut self, v: &Value) {
+        self.root.add(v);
+    }
+
+    /// Number of samples added so far.
+    pub fn samples(&self) -> usize {
+        self.root.seen
+    }
+
+    /// The inferred schema, in the format `Schema::compile` reads. Before
+    /// any sample is added this is `true`, which accepts everything.
+    pub fn to_schema(&self) -> Value {
+        self.root.to_schema()
+    }
+
+    /// Rust definitions for the samples, rooted at a struct called `name`.
+    /// The samples must be objects; otherwise only the `use` lines are
+    /// returned.
+    pub fn to_rust(&self, name: &str) -> String {
+        let mut gen = Generator::new();
+        if let Some(fields) = &self.root.object {
+            gen.define(name, fields);
+        }
+
+        let mut out = String::new();
+        if gen.uses_time {
+            out += "use std::time::SystemTime;\n";
+        }
+        out += "use value::from_value::FromValue;\n";
+        out += "use value::into_value::IntoValue;\n";
+        match (gen.uses_bytes, gen.uses_value) {
+            (true, true) => out += "use value::value::{Bytes, Value};\n",
+            (true, false) => out += "use value::value::Bytes;\n",
+            (false, true) => out += "use value::value::Value;\n",
+            (false, false) => {}
+        }
+        out += "use value_derive::{FromValue, IntoValue};\n";
+        // Each struct is pushed after the ones it uses; write it before them.
+        for def in gen.definitions.iter().rev() {
+            out += "\n";
+            out += def;
+        }
+        out
+    }
+}
+
+// Names the generated code imports or uses; structs must not take them.
+const RESERVED_NAMES: &[&str] = &[
+    "Bytes",
+    "FromValue",
+    "IntoValue",
+    "Option",
+    "String",
+    "SystemTime",
+    "Value",
+    "Vec",
+];
+
+#[derive(Default)]
+struct Generator {
+    definitions: Vec<String>,
+    struct_names: HashSet<String>,
+    uses_time: bool,
+    uses_bytes: bool,
+    uses_value: bool,
+}
+
+impl Generator {
+    fn new() -> Generator {
+        Generator {
+            struct_names: RESERVED_NAMES.iter().map(|n| n.to_string()).collect(),
+            ..Generator::default()
+        }
+    }
+
+    // Writes a struct for `fields` and returns its name, which is `name`
+    // made unique.
+    fn define(&mut self, name: &str, fields: &Fields) -> String {
+        let mut struct_name = name.to_string();
+        let mut n = 2;
+        while !self.struct_names.insert(struct_name.clone()) {
+            struct_name = format!("{}{}", name, n);
+            n += 1;
+        }
+
+        let mut field_names = HashSet::new();
+        let mut body = String::new();
+        for (key, shape) in &fields.fields {
+            let mut field = field_name(key);
+            let mut n = 2;
+            while !field_names.insert(field.clone()) {
+                field = format!("{}_{}", field_name(key), n);
+                n += 1;
+            }
+            let optional = shape.seen < fields.count;
+            let ty = self.field_type(&pascal_case(key), shape, optional);
+            if field != key.as_str() {
+                let _ = writeln!(body, "    #[value(rename = {:?})]", key);
+            }
+            let _ = writeln!(body, "    pub {}: {},", field, ty);
+        }
+
+        self.definitions.push(format!(
+            "#[derive(Debug, Clone, Default, FromValue, IntoValue)]\npub struct {} {{\n{}}}\n",
+            struct_name, body
+        ));
+        struct_name
+    }
+
+    fn field_type(&mut self, name: &str, shape: &Shape, optional: bool) -> String {
+        let mut kinds = shape.type_names();
+        kinds.retain(|k| *k != "null");
+        let ty = match kinds.as_slice() {
+            // Only ever null: nothing to go on.
+            [] => return self.value_type(),
+            ["boolean"] => "bool".to_string(),
+            ["integer"] => match shape.integers {
+                // Neither i64 nor u64 holds both ends.
+                Some((min, max)) if min < 0 && max > i64::MAX as i128 => return self.value_type(),
+                Some((_, max)) if max > i64::MAX as i128 => "u64".to_string(),
+                _ => "i64".to_string(),
+            },
+            ["number"] => "f64".to_string(),
+            ["string"] => "String".to_string(),
+            ["binary"] => {
+                self.uses_bytes = true;
+                "Bytes".to_string()
+            }
+            ["timestamp"] => {
+                self.uses_time = true;
+                return "Option<SystemTime>".to_string();
+            }
+            ["array"] => {
+                let item_type = match &shape.items {
+                    Some(items) if items.seen > 0 => {
+                        self.field_type(&format!("{}Item", name), items, items.null)
+                    }
+                    _ => self.value_type(),
+                };
+                format!("Vec<{}>", item_type)
+            }
+            ["object"] => match &shape.object {
+                Some(fields) => self.define(name, fields),
+                None => self.value_type(),
+            },
+            _ => return self.value_type(),
+        };
+        if optional || shape.null {
+            format!("Option<{}>", ty)
+        } else {
+            ty
+        }
+    }
+
+    fn value_type(&mut self) -> String {
+        self.uses_value = true;
+        "Value".to_string()
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "try",
+    "type", "unsafe", "use", "where", "while", "yield",
+];
+
+// A snake_case field name for `key`: `userId` becomes `user_id`, `type`
+// becomes `type_` and characters that cannot appear in a name become `_`.
+fn field_name(key: &str) -> String {
+    let mut result = String::new();
+    let mut prev_lower = false;
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            if prev_lower {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+            prev_lower = false;
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            result.push(c);
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        } else if !result.ends_with('_') {
+            result.push('_');
+            prev_lower = false;
+        }
+    }
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    if result == "_" {
+        result = "field".to_string();
+    }
+    if KEYWORDS.contains(&result.as_str()) {
+        result.push('_');
+    }
+    result
+}
+
+// A PascalCase type name for `key`: `user_profile` becomes `UserProfile`.
+fn pascal_case(key: &str) -> String {
+    let mut result = String::new();
+    let mut upper = true;
+    for c in key.chars() {
+        if c.is_ascii_alphanumeric() {
+            if upper {
+                result.push(c.to_ascii_uppercase());
+            } else {
+                result.push(c);
+            }
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, 'T');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schema;
+
+    fn infer(samples: &[Value]) -> Inference {
+        let mut inference = Inference::new();
+        for v in samples {
+            inference.add(v);
+        }
+        inference
+    }
+
+    fn samples() -> Vec<Value> {
+        vec![
+            value!({
+                "id": 1,
+                "userName": "a",
+                "score": 1,
+                "tags": ["x"],
+                "profile": {"age": 30, "type": "admin"},
+                "extra": null
+            }),
+            value!({
+                "id": 2,
+                "userName": "b",
+                "score": 2.5,
+                "tags": [],
+                "profile": {"age": 31},
+                "note": "n"
+            }),
+            value!({
+                "id": (u64::MAX),
+                "userName": "c",
+                "score": 3,
+                "tags": ["y", "z"],
+                "profile": {"age": null, "type": "user"},
+                "mixed": 1
+            }),
+            value!({
+                "id": 4,
+                "userName": "d",
+                "score": 4,
+                "profile": {"age": 33},
+                "mixed": "one"
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_to_schema() {
+        let samples = samples();
+        let inference = infer(&samples);
+        assert_eq!(inference.samples(), 4);
+        let schema = inference.to_schema();
+        assert_eq!(
+            schema,
+            value!({
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer"},
+                    "userName": {"type": "string"},
+                    "score": {"type": "number"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "profile": {
+                        "type": "object",
+                        "properties": {
+                            "age": {"type": ["null", "integer"]},
+                            "type": {"type": "string"}
+                        },
+                        "required": ["age"]
+                    },
+                    "extra": {"type": "null"},
+                    "note": {"type": "string"},
+                    "mixed": {"type": ["integer", "string"]}
+                },
+                "required": ["id", "userName", "score", "profile"]
+            })
+        );
+
+        let compiled = Schema::compile(&schema).unwrap();
+        assert!(samples.iter().all(|v| compiled.is_valid(v)));
+        assert!(!compiled.is_valid(&value!({"id": 1})));
+        assert_eq!(Inference::new().to_schema(), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_to_rust() {
+        let mut samples = samples();
+        if let Value::Object(o) = &mut samples[0] {
+            o.insert("at".to_string(), Value::Timestamp { secs: 0, nanos: 0 });
+            o.insert("raw".to_string(), Value::Binary(vec![1]));
+            o.insert(
+                "events".to_string(),
+                value!([{"kind": "a", "n": 1}, {"kind": "b"}]),
+            );
+        }
+        let expected = "\
+use std::time::SystemTime;
+use value::from_value::FromValue;
+use value::into_value::IntoValue;
+use value::value::{Bytes, Value};
+use value_derive::{FromValue, IntoValue};
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct Event {
+    pub id: u64,
+    #[value(rename = \"userName\")]
+    pub user_name: String,
+    pub score: f64,
+    pub tags: Option<Vec<String>>,
+    pub profile: Profile,
+    pub extra: Value,
+    pub at: Option<SystemTime>,
+    pub raw: Option<Bytes>,
+    pub events: Option<Vec<EventsItem>>,
+    pub note: Option<String>,
+    pub mixed: Value,
+}
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct EventsItem {
+    pub kind: String,
+    pub n: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct Profile {
+    pub age: Option<i64>,
+    #[value(rename = \"type\")]
+    pub type_: Option<String>,
+}
+";
+        assert_eq!(infer(&samples).to_rust("Event"), expected);
+    }
+
+    #[test]
+    fn test_to_rust_reserved_names() {
+        let sample = value!({
+            "value": {"a": 1},
+            "bytes": {"b": "x"},
+            "string": {"c": true},
+            "vec": {"d": 1.5},
+            "option": {"e": "y"},
+            "systemTime": {"f": 2},
+            "fromValue": {"g": 3},
+            "intoValue": {"h": 4}
+        });
+        let code = infer(&[sample]).to_rust("Value");
+        let mut structs: Vec<&str> = code
+            .lines()
+            .filter_map(|l| l.strip_prefix("pub struct "))
+            .map(|l| l.trim_end_matches(" {"))
+            .collect();
+        structs.sort();
+        assert_eq!(
+            structs,
+            vec![
+                "Bytes2",
+                "FromValue2",
+                "IntoValue2",
+                "Option2",
+                "String2",
+                "SystemTime2",
+                "Value2",
+                "Value3",
+                "Vec2"
+            ]
+        );
+        assert!(code.contains("    pub value: Value3,\n"));
+    }
+
+    #[test]
+    fn test_to_rust_integer_range() {
+        let samples = [
+            value!({"small": -1, "big": 1, "mixed": -1}),
+            value!({"small": 2, "big": (u64::MAX), "mixed": (u64::MAX)}),
+        ];
+        let code = infer(&samples).to_rust("Range");
+        assert!(code.contains("    pub small: i64,\n"));
+        assert!(code.contains("    pub big: u64,\n"));
+        assert!(code.contains("    pub mixed: Value,\n"));
+        assert!(code.contains("use value::value::Value;\n"));
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(field_name("userId"), "user_id");
+        assert_eq!(field_name("HTTPCode"), "httpcode");
+        assert_eq!(field_name("content-type"), "content_type");
+        assert_eq!(field_name("2fa"), "_2fa");
+        assert_eq!(field_name("match"), "match_");
+        assert_eq!(field_name("-"), "field");
+        assert_eq!(pascal_case("user_profile"), "UserProfile");
+        assert_eq!(pascal_case("9lives"), "T9lives");
+    }
+}
@@ -1,7 +1,11 @@
+use crate::object_map::ObjectMap;
+use crate::time;
 use crate::value::*;
 use std::collections::HashMap;
+use std::time::SystemTime;
 
-pub trait IntoValue: Default {
+#[allow(clippy::wrong_self_convention)]
+pub trait IntoValue {
     fn into_value(&self) -> Value;
 }
 
@@ -29,6 +33,19 @@ impl IntoValue for &str {
     }
 }
 
+impl IntoValue for Bytes {
+    fn into_value(&self) -> Value {
+        Value::Binary(self.0.clone())
+    }
+}
+
+impl IntoValue for SystemTime {
+    fn into_value(&self) -> Value {
+        let (secs, nanos) = time::split_time(*self);
+        Value::Timestamp { secs, nanos }
+    }
+}
+
 impl<T> IntoValue for Vec<T>
 where
     T: IntoValue + Clone,
@@ -42,13 +59,28 @@ where
     }
 }
 
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(&self) -> Value {
+        match self {
+            Some(v) => v.into_value(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl IntoValue for ObjectMap {
+    fn into_value(&self) -> Value {
+        Value::Object(self.clone())
+    }
+}
+
 impl<HK, HV> IntoValue for HashMap<HK, HV>
 where
     HK: std::string::ToString + std::hash::Hash + std::cmp::Eq,
     HV: IntoValue,
 {
     fn into_value(&self) -> Value {
-        let mut result: HashMap<String, Value> = HashMap::new();
+        let mut result = ObjectMap::with_capacity(self.len());
         for key in self.keys() {
             let value = self.get(key).unwrap();
             result.insert(key.to_string(), value.into_value());
@@ -59,75 +91,115 @@ where
 
 impl IntoValue for u8 {
     fn into_value(&self) -> Value {
-        Value::Number(self.to_string())
+        Value::UInt(u64::from(*self))
     }
 }
 
 impl IntoValue for i8 {
     fn into_value(&self) -> Value {
-        Value::Number(self.to_string())
+        Value::Int(i64::from(*self))
     }
 }
 
 impl IntoValue for u16 {
     fn into_value(&self) -> Value {
-        Value::Number(self.to_string())
+        Value::UInt(u64::from(*self))
     }
 }
 
 impl IntoValue for i16 {
     fn into_value(&self) -> Value {
-        Value::Number(self.to_string())
+        Value::Int(i64::from(*self))
     }
 }
 
 impl IntoValue for u32 {
     fn into_value(&self) -> Value {
-        Value::Number(self.to_string())
+        Value::UInt(u64::from(*self))
     }
 }
 
 impl IntoValue for i32 {
     fn into_value(&self) -> Value {
-        Value::Number(self.to_string())
+        Value::Int(i64::from(*self))
     }
 }
 
 impl IntoValue for u64 {
     fn into_value(&self) -> Value {
-        Value::Number(self.to_string())
+        Value::UInt(*self)
     }
 }
 
 impl IntoValue for i64 {
     fn into_value(&self) -> Value {
-        Value::Number(self.to_string())
+        Value::Int(*self)
+    }
+}
+
+impl IntoValue for usize {
+    fn into_value(&self) -> Value {
+        Value::UInt(*self as u64)
+    }
+}
+
+impl IntoValue for isize {
+    fn into_value(&self) -> Value {
+        Value::Int(*self as i64)
     }
 }
 
 impl IntoValue for f32 {
     fn into_value(&self) -> Value {
-        Value::Number(self.to_string())
+        Value::F32(*self)
     }
 }
 
 impl IntoValue for f64 {
     fn into_value(&self) -> Value {
-        Value::Number(self.to_string())
+        Value::F64(*self)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::from_value::FromValue;
 
     #[test]
     fn vec_into_value() {
         let v1: Value = Value::Null;
-        let mut arr: Vec<Value> = Vec::new();
-        arr.push(v1);
+        let arr: Vec<Value> = vec![v1];
         println!("arr = {:?}", arr);
-        let result = arr.into_value();
+        let _result = arr.into_value();
         // println!("arr = {:?}", arr);
     }
+
+    #[test]
+    fn bytes_into_value() {
+        let b = Bytes(vec![1, 2, 3]);
+        match b.into_value() {
+            Value::Binary(v) => assert_eq!(v, vec![1, 2, 3]),
+            v => panic!("unexpected value {:?}", v),
+        }
+        let back: Bytes = Value::Binary(vec![1, 2, 3]).from_value();
+        assert_eq!(back, b);
+        let as_vec: Vec<u8> = Value::Binary(vec![1, 2, 3]).from_value();
+        assert_eq!(as_vec, vec![1, 2, 3]);
+        assert_eq!(format!("{}", Value::Binary(vec![0xde, 0xad])), "\"dead\"");
+    }
+
+    #[test]
+    fn system_time_into_value() {
+        let t = SystemTime::UNIX_EPOCH + std::time::Duration::new(1, 5);
+        match t.into_value() {
+            Value::Timestamp { secs, nanos } => {
+                assert_eq!(secs, 1);
+                assert_eq!(nanos, 5);
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+        let back: SystemTime = t.into_value().from_value();
+        assert_eq!(back, t);
+    }
 }
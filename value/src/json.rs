@@ -0,0 +1,502 @@
+//! JSON text (RFC 8259) to and from `Value`.
+//!
+//! Numbers are kept exact where `Value` can hold them: integers become
+//! `Int`, or `UInt` above `i64::MAX`, and anything with a fraction or an
+//! exponent becomes the nearest `F64`, which writes back as the same text.
+//! Integers outside the `i64`/`u64` range and `-0` fall back to `F64`.
+//!
+//! Writing uses the compact `Formatter`, see `format` for how non-JSON
+//! variants are rendered.
+
+use crate::format::Formatter;
+use crate::object_map::ObjectMap;
+use crate::value::Value;
+use std::error::Error as StdError;
+use std::fmt;
+
+/// Nesting depth at which parsing gives up instead of risking the stack.
+const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonErrorKind {
+    UnexpectedEnd,
+    UnexpectedChar(char),
+    InvalidNumber,
+    InvalidEscape,
+    InvalidUnicode,
+    ControlCharacter,
+    TrailingCharacters,
+    DepthLimitExceeded,
+}
+
+impl fmt::Display for JsonErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonErrorKind::UnexpectedEnd => write!(f, "UnexpectedEnd"),
+            JsonErrorKind::UnexpectedChar(c) => write!(f, "UnexpectedChar: {:?}", c),
+            JsonErrorKind::InvalidNumber => write!(f, "InvalidNumber"),
+            JsonErrorKind::InvalidEscape => write!(f, "InvalidEscape"),
+            JsonErrorKind::InvalidUnicode => write!(f, "InvalidUnicode"),
+            JsonErrorKind::ControlCharacter => write!(f, "ControlCharacter"),
+            JsonErrorKind::TrailingCharacters => write!(f, "TrailingCharacters"),
+            JsonErrorKind::DepthLimitExceeded => write!(f, "DepthLimitExceeded"),
+        }
+    }
+}
+
+/// A parse failure. `line` and `column` are 1-based, the column counts
+/// characters rather than bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    pub kind: JsonErrorKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.kind, self.line, self.column
+        )
+    }
+}
+
+impl StdError for JsonError {}
+
+impl Value {
+    pub fn from_json_str(s: &str) -> Result<Value, JsonError> {
+        let mut parser = Parser {
+            input: s,
+            bytes: s.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+        parser.skip_whitespace();
+        let v = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.bytes.len() {
+            return Err(parser.error(JsonErrorKind::TrailingCharacters));
+        }
+        Ok(v)
+    }
+
+    pub fn to_json_string(&self) -> String {
+        Formatter::compact().format(self)
+    }
+
+    pub fn to_json_string_pretty(&self) -> String {
+        Formatter::pretty().format(self)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, kind: JsonErrorKind) -> JsonError {
+        self.error_at(self.pos, kind)
+    }
+
+    fn error_at(&self, pos: usize, kind: JsonErrorKind) -> JsonError {
+        let before = &self.input[..pos];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let column = before[line_start..].chars().count() + 1;
+        JsonError { kind, line, column }
+    }
+
+    // Error for the character at the current position, or for running out
+    // of input.
+    fn unexpected(&self) -> JsonError {
+        match self.input[self.pos..].chars().next() {
+            Some(c) => self.error(JsonErrorKind::UnexpectedChar(c)),
+            None => self.error(JsonErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), JsonError> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, JsonError> {
+        match self.peek() {
+            Some(b'n') => self.parse_literal("null", Value::Null),
+            Some(b't') => self.parse_literal("true", Value::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Value::Bool(false)),
+            Some(b'"') => Ok(Value::String(self.parse_string()?)),
+            Some(b'[') => self.nested(Parser::parse_array),
+            Some(b'{') => self.nested(Parser::parse_object),
+            Some(b'-') | Some(b'0'..=b'9') => self.parse_number(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Parser<'a>) -> Result<Value, JsonError>,
+    ) -> Result<Value, JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(JsonErrorKind::DepthLimitExceeded));
+        }
+        self.depth += 1;
+        let v = parse(self)?;
+        self.depth -= 1;
+        Ok(v)
+    }
+
+    fn parse_literal(&mut self, literal: &str, v: Value) -> Result<Value, JsonError> {
+        for &b in literal.as_bytes() {
+            if self.peek() != Some(b) {
+                return Err(self.unexpected());
+            }
+            self.pos += 1;
+        }
+        Ok(v)
+    }
+
+    fn parse_array(&mut self) -> Result<Value, JsonError> {
+        self.expect(b'[')?;
+        let mut result = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(result));
+        }
+        loop {
+            self.skip_whitespace();
+            result.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(result));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, JsonError> {
+        self.expect(b'{')?;
+        let mut result = ObjectMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(result));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.unexpected());
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+            let v = self.parse_value()?;
+            result.insert(key, v);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(result));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut result = String::new();
+        loop {
+            // Copy the run up to the next quote, backslash or control
+            // character in one go; it is valid UTF-8 since the input is.
+            let start = self.pos;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            result.push_str(&self.input[start..self.pos]);
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(result);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let c = self.parse_escape()?;
+                    result.push(c);
+                }
+                Some(_) => return Err(self.error(JsonErrorKind::ControlCharacter)),
+                None => return Err(self.error(JsonErrorKind::UnexpectedEnd)),
+            }
+        }
+    }
+
+    // Called just after the backslash.
+    fn parse_escape(&mut self) -> Result<char, JsonError> {
+        let escape_start = self.pos - 1;
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let unit = self.parse_hex4()?;
+                return match unit {
+                    0xd800..=0xdbff => {
+                        if self.bytes[self.pos..].starts_with(b"\\u") {
+                            self.pos += 2;
+                            let low = self.parse_hex4()?;
+                            if (0xdc00..=0xdfff).contains(&low) {
+                                let code = 0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00);
+                                // Always a valid scalar value by construction.
+                                Ok(std::char::from_u32(code).unwrap_or('\u{fffd}'))
+                            } else {
+                                Err(self.error_at(escape_start, JsonErrorKind::InvalidUnicode))
+                            }
+                        } else {
+                            Err(self.error_at(escape_start, JsonErrorKind::InvalidUnicode))
+                        }
+                    }
+                    0xdc00..=0xdfff => {
+                        Err(self.error_at(escape_start, JsonErrorKind::InvalidUnicode))
+                    }
+                    // Anything outside the surrogate range is a scalar value.
+                    _ => Ok(std::char::from_u32(unit).unwrap_or('\u{fffd}')),
+                };
+            }
+            Some(_) => return Err(self.error_at(escape_start, JsonErrorKind::InvalidEscape)),
+            None => return Err(self.error(JsonErrorKind::UnexpectedEnd)),
+        };
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError> {
+        let mut unit = 0;
+        for _ in 0..4 {
+            let digit = match self.peek() {
+                Some(b) => (b as char).to_digit(16),
+                None => return Err(self.error(JsonErrorKind::UnexpectedEnd)),
+            };
+            match digit {
+                Some(d) => unit = unit * 16 + d,
+                None => return Err(self.error(JsonErrorKind::InvalidEscape)),
+            }
+            self.pos += 1;
+        }
+        Ok(unit)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, JsonError> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => {
+                self.pos += 1;
+                if let Some(b'0'..=b'9') = self.peek() {
+                    return Err(self.error_at(start, JsonErrorKind::InvalidNumber));
+                }
+            }
+            Some(b'1'..=b'9') => self.skip_digits(),
+            _ => return Err(self.error_at(start, JsonErrorKind::InvalidNumber)),
+        }
+
+        let mut integral = true;
+        if self.peek() == Some(b'.') {
+            integral = false;
+            self.pos += 1;
+            if !self.at_digit() {
+                return Err(self.error_at(start, JsonErrorKind::InvalidNumber));
+            }
+            self.skip_digits();
+        }
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            integral = false;
+            self.pos += 1;
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if !self.at_digit() {
+                return Err(self.error_at(start, JsonErrorKind::InvalidNumber));
+            }
+            self.skip_digits();
+        }
+
+        let text = &self.input[start..self.pos];
+        if integral && text != "-0" {
+            if let Ok(n) = text.parse::<i64>() {
+                return Ok(Value::Int(n));
+            }
+            if let Ok(n) = text.parse::<u64>() {
+                return Ok(Value::UInt(n));
+            }
+        }
+        text.parse::<f64>()
+            .map(Value::F64)
+            .map_err(|_| self.error_at(start, JsonErrorKind::InvalidNumber))
+    }
+
+    fn at_digit(&self) -> bool {
+        matches!(self.peek(), Some(b'0'..=b'9'))
+    }
+
+    fn skip_digits(&mut self) {
+        while self.at_digit() {
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Value {
+        Value::from_json_str(s).unwrap()
+    }
+
+    fn error(s: &str) -> (JsonErrorKind, usize, usize) {
+        let e = Value::from_json_str(s).unwrap_err();
+        (e.kind, e.line, e.column)
+    }
+
+    #[test]
+    fn test_parse() {
+        let v = parse(r#" {"a": [1, -2, 3.5, true, null], "b": {"c": "d"}, "e": []} "#);
+        assert_eq!(
+            v,
+            value!({"a": [1, -2, 3.5, true, null], "b": {"c": "d"}, "e": []})
+        );
+        match &v {
+            Value::Object(o) => {
+                let keys: Vec<&str> = o.keys().map(|k| k.as_str()).collect();
+                assert_eq!(keys, vec!["a", "b", "e"]);
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+        assert_eq!(parse("\"x\""), value!("x"));
+        assert_eq!(parse("0"), Value::Int(0));
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert!(matches!(
+            parse("-9223372036854775808"),
+            Value::Int(i64::MIN)
+        ));
+        assert!(matches!(
+            parse("18446744073709551615"),
+            Value::UInt(u64::MAX)
+        ));
+        assert!(matches!(parse("18446744073709551616"), Value::F64(_)));
+        assert!(matches!(parse("1.0"), Value::F64(f) if f == 1.0));
+        assert!(matches!(parse("-0"), Value::F64(f) if f == 0.0 && f.is_sign_negative()));
+        assert!(matches!(parse("1E+2"), Value::F64(f) if f == 100.0));
+
+        for text in &["0.1", "1e300", "-2.2250738585072014e-308", "123456.789"] {
+            assert_eq!(parse(text).to_json_string(), *text);
+        }
+        assert_eq!(parse("1.5e3").to_json_string(), "1500.0");
+
+        for bad in &["01", "1.", ".5", "-", "1e", "+1", "0x1"] {
+            assert!(Value::from_json_str(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(
+            parse(r#""a\"b\\c\/d\b\f\n\r\t""#),
+            value!("a\"b\\c/d\u{8}\u{c}\n\r\t")
+        );
+        assert_eq!(parse(r#""é☃""#), value!("é☃"));
+        assert_eq!(parse(r#""\ud83d\ude00""#), value!("😀"));
+        assert_eq!(parse(r#""\uD83D\uDE00\u00e9\u0041""#), value!("😀éA"));
+
+        let text = value!("tab\there \u{1} \"q\" 😀").to_json_string();
+        assert_eq!(
+            Value::from_json_str(&text).unwrap(),
+            value!("tab\there \u{1} \"q\" 😀")
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(error(""), (JsonErrorKind::UnexpectedEnd, 1, 1));
+        assert_eq!(
+            error("[1,\n 2,\n x]"),
+            (JsonErrorKind::UnexpectedChar('x'), 3, 2)
+        );
+        assert_eq!(
+            error("{\"é\": tru}"),
+            (JsonErrorKind::UnexpectedChar('}'), 1, 10)
+        );
+        assert_eq!(error("[1] 2"), (JsonErrorKind::TrailingCharacters, 1, 5));
+        assert_eq!(error("[1,]"), (JsonErrorKind::UnexpectedChar(']'), 1, 4));
+        assert_eq!(
+            error("{\"a\" 1}"),
+            (JsonErrorKind::UnexpectedChar('1'), 1, 6)
+        );
+        assert_eq!(error("\"a\nb\""), (JsonErrorKind::ControlCharacter, 1, 3));
+        assert_eq!(error(r#""\x""#), (JsonErrorKind::InvalidEscape, 1, 2));
+        assert_eq!(error(r#""\ud83d""#), (JsonErrorKind::InvalidUnicode, 1, 2));
+        assert_eq!(error(r#""\ude00""#), (JsonErrorKind::InvalidUnicode, 1, 2));
+        assert_eq!(error("\"abc"), (JsonErrorKind::UnexpectedEnd, 1, 5));
+        assert_eq!(
+            error(&"[".repeat(MAX_DEPTH + 1)).0,
+            JsonErrorKind::DepthLimitExceeded
+        );
+        assert_eq!(
+            Value::from_json_str("nul").unwrap_err().to_string(),
+            "UnexpectedEnd at line 1 column 4"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let v = value!({
+            "name": "x\ny",
+            "list": [1, 2.5, -3, {"deep": [null, false]}],
+            "empty": {},
+            "big": u64::MAX,
+        });
+        assert_eq!(Value::from_json_str(&v.to_json_string()).unwrap(), v);
+        assert_eq!(Value::from_json_str(&v.to_json_string_pretty()).unwrap(), v);
+    }
+}
@@ -0,0 +1,578 @@
+//! JSON Patch (RFC 6902) operations and a structural diff on `Value` trees.
+//!
+//! Operations are addressed by JSON Pointer (RFC 6901), which reaches `Map`
+//! entries with string keys as `Value::pointer` does. `Value::diff` turns
+//! the difference of two trees into `add`, `remove` and `replace`
+//! operations, `Value::apply_patch` runs any list of operations and leaves
+//! the target untouched when one of them fails.
+
+use crate::into_value::IntoValue;
+use crate::object_map::ObjectMap;
+use crate::pointer::{escape_pointer_token, map_key_position, parse_array_index, parse_pointer};
+use crate::value::Value;
+use std::error::Error as StdError;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOp {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    InvalidPointer(String),
+    PathNotFound(String),
+    IndexOutOfRange(String),
+    NotAContainer(String),
+    MoveIntoChild(String),
+    TestFailed(String),
+    InvalidOperation(String),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchError::InvalidPointer(p) => write!(f, "InvalidPointer: {}", p),
+            PatchError::PathNotFound(p) => write!(f, "PathNotFound: {}", p),
+            PatchError::IndexOutOfRange(p) => write!(f, "IndexOutOfRange: {}", p),
+            PatchError::NotAContainer(p) => write!(f, "NotAContainer: {}", p),
+            PatchError::MoveIntoChild(p) => write!(f, "MoveIntoChild: {}", p),
+            PatchError::TestFailed(p) => write!(f, "TestFailed: {}", p),
+            PatchError::InvalidOperation(p) => write!(f, "InvalidOperation: {}", p),
+        }
+    }
+}
+
+impl StdError for PatchError {}
+
+impl PatchOp {
+    pub fn op(&self) -> &'static str {
+        match self {
+            PatchOp::Add { .. } => "add",
+            PatchOp::Remove { .. } => "remove",
+            PatchOp::Replace { .. } => "replace",
+            PatchOp::Move { .. } => "move",
+            PatchOp::Copy { .. } => "copy",
+            PatchOp::Test { .. } => "test",
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOp::Add { path, .. }
+            | PatchOp::Remove { path }
+            | PatchOp::Replace { path, .. }
+            | PatchOp::Move { path, .. }
+            | PatchOp::Copy { path, .. }
+            | PatchOp::Test { path, .. } => path,
+        }
+    }
+
+    /// Reads one operation object such as
+    /// `{"op": "replace", "path": "/a", "value": 1}`.
+    pub fn parse(v: &Value) -> Result<PatchOp, PatchError> {
+        let o = match v {
+            Value::Object(o) => o,
+            _ => return Err(PatchError::InvalidOperation(v.kind().to_string())),
+        };
+        let field = |name: &str| -> Result<String, PatchError> {
+            match o.get(name) {
+                Some(Value::String(s)) => Ok(s.clone()),
+                _ => Err(PatchError::InvalidOperation(format!("missing {}", name))),
+            }
+        };
+        let value = || -> Result<Value, PatchError> {
+            o.get("value")
+                .cloned()
+                .ok_or_else(|| PatchError::InvalidOperation("missing value".to_string()))
+        };
+        let path = field("path")?;
+        let op = match field("op")?.as_str() {
+            "add" => PatchOp::Add {
+                path,
+                value: value()?,
+            },
+            "remove" => PatchOp::Remove { path },
+            "replace" => PatchOp::Replace {
+                path,
+                value: value()?,
+            },
+            "move" => PatchOp::Move {
+                from: field("from")?,
+                path,
+            },
+            "copy" => PatchOp::Copy {
+                from: field("from")?,
+                path,
+            },
+            "test" => PatchOp::Test {
+                path,
+                value: value()?,
+            },
+            other => return Err(PatchError::InvalidOperation(other.to_string())),
+        };
+        Ok(op)
+    }
+
+    /// Reads a patch document, an array of operation objects.
+    pub fn parse_all(v: &Value) -> Result<Vec<PatchOp>, PatchError> {
+        match v {
+            Value::Array(arr) => arr.iter().map(PatchOp::parse).collect(),
+            _ => Err(PatchError::InvalidOperation(v.kind().to_string())),
+        }
+    }
+}
+
+impl IntoValue for PatchOp {
+    fn into_value(&self) -> Value {
+        let mut o = ObjectMap::new();
+        o.insert("op".to_string(), Value::String(self.op().to_string()));
+        match self {
+            PatchOp::Move { from, .. } | PatchOp::Copy { from, .. } => {
+                o.insert("from".to_string(), Value::String(from.clone()));
+            }
+            _ => {}
+        }
+        o.insert("path".to_string(), Value::String(self.path().to_string()));
+        match self {
+            PatchOp::Add { value, .. }
+            | PatchOp::Replace { value, .. }
+            | PatchOp::Test { value, .. } => {
+                o.insert("value".to_string(), value.clone());
+            }
+            _ => {}
+        }
+        Value::Object(o)
+    }
+}
+
+impl fmt::Display for PatchOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchOp::Add { path, value }
+            | PatchOp::Replace { path, value }
+            | PatchOp::Test { path, value } => {
+                write!(f, "{} {} {}", self.op(), path, value)
+            }
+            PatchOp::Remove { path } => write!(f, "remove {}", path),
+            PatchOp::Move { from, path } | PatchOp::Copy { from, path } => {
+                write!(f, "{} {} -> {}", self.op(), from, path)
+            }
+        }
+    }
+}
+
+impl Value {
+    /// Operations that turn `from` into `to`.
+    ///
+    /// Objects are compared member by member, arrays position by position;
+    /// extra trailing elements are added or removed from the end.
+    ///
+    /// Only `add`, `remove` and `replace` are produced. A value that moved
+    /// or was copied shows up as a `remove` and an `add`, or as an `add`
+    /// alone, never as `move` or `copy`. Maps and other values that are not
+    /// objects or arrays are replaced whole when they differ.
+    pub fn diff(from: &Value, to: &Value) -> Vec<PatchOp> {
+        let mut ops = Vec::new();
+        diff_into(from, to, String::new(), &mut ops);
+        ops
+    }
+
+    /// Applies `ops` in order. If any operation fails the value is left as
+    /// it was and the error of the failing operation is returned.
+    pub fn apply_patch(&mut self, ops: &[PatchOp]) -> Result<(), PatchError> {
+        let mut doc = self.clone();
+        for op in ops {
+            apply_op(&mut doc, op)?;
+        }
+        *self = doc;
+        Ok(())
+    }
+}
+
+fn diff_into(from: &Value, to: &Value, path: String, ops: &mut Vec<PatchOp>) {
+    if from == to {
+        return;
+    }
+    match (from, to) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, _) in a {
+                if !b.contains_key(key) {
+                    ops.push(PatchOp::Remove {
+                        path: format!("{}/{}", path, escape_pointer_token(key)),
+                    });
+                }
+            }
+            for (key, new) in b {
+                let child = format!("{}/{}", path, escape_pointer_token(key));
+                match a.get(key) {
+                    Some(old) => diff_into(old, new, child, ops),
+                    None => ops.push(PatchOp::Add {
+                        path: child,
+                        value: new.clone(),
+                    }),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            let common = a.len().min(b.len());
+            for i in 0..common {
+                diff_into(&a[i], &b[i], format!("{}/{}", path, i), ops);
+            }
+            for (i, new) in b.iter().enumerate().skip(common) {
+                ops.push(PatchOp::Add {
+                    path: format!("{}/{}", path, i),
+                    value: new.clone(),
+                });
+            }
+            for i in (common..a.len()).rev() {
+                ops.push(PatchOp::Remove {
+                    path: format!("{}/{}", path, i),
+                });
+            }
+        }
+        _ => ops.push(PatchOp::Replace {
+            path,
+            value: to.clone(),
+        }),
+    }
+}
+
+fn split_pointer(pointer: &str) -> Result<Vec<String>, PatchError> {
+    parse_pointer(pointer).ok_or_else(|| PatchError::InvalidPointer(pointer.to_string()))
+}
+
+fn resolve_mut<'v>(
+    doc: &'v mut Value,
+    tokens: &[String],
+    pointer: &str,
+) -> Result<&'v mut Value, PatchError> {
+    let mut target = doc;
+    for token in tokens {
+        target = target
+            .child_mut(token)
+            .ok_or_else(|| PatchError::PathNotFound(pointer.to_string()))?;
+    }
+    Ok(target)
+}
+
+fn add(doc: &mut Value, pointer: &str, value: Value) -> Result<(), PatchError> {
+    let tokens = split_pointer(pointer)?;
+    let (last, parents) = match tokens.split_last() {
+        Some(split) => split,
+        None => {
+            *doc = value;
+            return Ok(());
+        }
+    };
+    match resolve_mut(doc, parents, pointer)? {
+        Value::Object(o) => {
+            o.insert(last.clone(), value);
+            Ok(())
+        }
+        Value::Map(pairs) => {
+            match map_key_position(pairs, last) {
+                Some(i) => pairs[i].1 = value,
+                None => pairs.push((Value::String(last.clone()), value)),
+            }
+            Ok(())
+        }
+        Value::Array(arr) => {
+            let i = if last == "-" {
+                arr.len()
+            } else {
+                parse_array_index(last)
+                    .ok_or_else(|| PatchError::InvalidPointer(pointer.to_string()))?
+            };
+            if i > arr.len() {
+                return Err(PatchError::IndexOutOfRange(pointer.to_string()));
+            }
+            arr.insert(i, value);
+            Ok(())
+        }
+        _ => Err(PatchError::NotAContainer(pointer.to_string())),
+    }
+}
+
+fn remove(doc: &mut Value, pointer: &str) -> Result<Value, PatchError> {
+    split_pointer(pointer)?;
+    doc.remove_at_path(pointer)
+        .ok_or_else(|| PatchError::PathNotFound(pointer.to_string()))
+}
+
+fn apply_op(doc: &mut Value, op: &PatchOp) -> Result<(), PatchError> {
+    match op {
+        PatchOp::Add { path, value } => add(doc, path, value.clone()),
+        PatchOp::Remove { path } => remove(doc, path).map(|_| ()),
+        PatchOp::Replace { path, value } => {
+            let tokens = split_pointer(path)?;
+            *resolve_mut(doc, &tokens, path)? = value.clone();
+            Ok(())
+        }
+        PatchOp::Move { from, path } => {
+            if from == path {
+                return resolve_mut(doc, &split_pointer(from)?, from).map(|_| ());
+            }
+            if path.starts_with(&format!("{}/", from)) {
+                return Err(PatchError::MoveIntoChild(path.clone()));
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        PatchOp::Copy { from, path } => {
+            let value = doc
+                .pointer(from)
+                .cloned()
+                .ok_or_else(|| PatchError::PathNotFound(from.clone()))?;
+            add(doc, path, value)
+        }
+        PatchOp::Test { path, value } => match doc.pointer(path) {
+            Some(current) if current == value => Ok(()),
+            Some(_) => Err(PatchError::TestFailed(path.clone())),
+            None => Err(PatchError::PathNotFound(path.clone())),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(entries: Vec<(&str, Value)>) -> Value {
+        Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_string())
+    }
+
+    #[test]
+    fn test_apply_operations() {
+        let mut doc = object(vec![
+            ("foo", Value::Array(vec![s("bar"), s("baz")])),
+            ("qux", object(vec![("thud", s("fred"))])),
+        ]);
+        let ops = vec![
+            PatchOp::Add {
+                path: "/foo/1".to_string(),
+                value: s("qux"),
+            },
+            PatchOp::Remove {
+                path: "/foo/0".to_string(),
+            },
+            PatchOp::Replace {
+                path: "/qux/thud".to_string(),
+                value: Value::Int(1),
+            },
+            PatchOp::Move {
+                from: "/qux/thud".to_string(),
+                path: "/moved".to_string(),
+            },
+            PatchOp::Copy {
+                from: "/foo".to_string(),
+                path: "/foo2".to_string(),
+            },
+            PatchOp::Test {
+                path: "/moved".to_string(),
+                value: Value::UInt(1),
+            },
+            PatchOp::Add {
+                path: "/foo/-".to_string(),
+                value: Value::Null,
+            },
+        ];
+        doc.apply_patch(&ops).unwrap();
+        assert_eq!(
+            doc,
+            object(vec![
+                ("foo", Value::Array(vec![s("qux"), s("baz"), Value::Null])),
+                ("qux", object(vec![])),
+                ("moved", Value::Int(1)),
+                ("foo2", Value::Array(vec![s("qux"), s("baz")])),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_apply_is_atomic() {
+        let original = object(vec![("a", Value::Int(1))]);
+        let mut doc = original.clone();
+        let ops = vec![
+            PatchOp::Replace {
+                path: "/a".to_string(),
+                value: Value::Int(2),
+            },
+            PatchOp::Test {
+                path: "/a".to_string(),
+                value: Value::Int(3),
+            },
+        ];
+        assert_eq!(
+            doc.apply_patch(&ops),
+            Err(PatchError::TestFailed("/a".to_string()))
+        );
+        assert_eq!(doc, original);
+
+        let bad = vec![PatchOp::Remove {
+            path: "/missing".to_string(),
+        }];
+        assert_eq!(
+            doc.apply_patch(&bad),
+            Err(PatchError::PathNotFound("/missing".to_string()))
+        );
+        let bad = vec![PatchOp::Move {
+            from: "/a".to_string(),
+            path: "/a/b".to_string(),
+        }];
+        assert_eq!(
+            doc.apply_patch(&bad),
+            Err(PatchError::MoveIntoChild("/a/b".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_diff_roundtrip() {
+        let from = object(vec![
+            ("a", Value::Int(1)),
+            (
+                "b",
+                Value::Array(vec![Value::Int(1), Value::Int(2), Value::Int(3)]),
+            ),
+            ("c/d", object(vec![("x", s("y"))])),
+        ]);
+        let to = object(vec![
+            ("b", Value::Array(vec![Value::Int(1), Value::Int(5)])),
+            ("c/d", object(vec![("x", s("z")), ("w", Value::Null)])),
+            ("e", Value::Bool(true)),
+        ]);
+        let ops = Value::diff(&from, &to);
+        assert_eq!(
+            ops,
+            vec![
+                PatchOp::Remove {
+                    path: "/a".to_string()
+                },
+                PatchOp::Replace {
+                    path: "/b/1".to_string(),
+                    value: Value::Int(5)
+                },
+                PatchOp::Remove {
+                    path: "/b/2".to_string()
+                },
+                PatchOp::Replace {
+                    path: "/c~1d/x".to_string(),
+                    value: s("z")
+                },
+                PatchOp::Add {
+                    path: "/c~1d/w".to_string(),
+                    value: Value::Null
+                },
+                PatchOp::Add {
+                    path: "/e".to_string(),
+                    value: Value::Bool(true)
+                },
+            ]
+        );
+
+        let mut applied = from.clone();
+        applied.apply_patch(&ops).unwrap();
+        assert_eq!(applied, to);
+        assert!(Value::diff(&to, &to).is_empty());
+
+        let moved = Value::diff(&object(vec![("a", s("x"))]), &object(vec![("b", s("x"))]));
+        let names: Vec<&str> = moved.iter().map(PatchOp::op).collect();
+        assert_eq!(names, vec!["remove", "add"]);
+    }
+
+    #[test]
+    fn test_map_paths() {
+        let mut doc = object(vec![(
+            "m",
+            Value::Map(vec![
+                (Value::Int(1), s("one")),
+                (s("a"), object(vec![("x", Value::Int(1))])),
+            ]),
+        )]);
+        let ops = vec![
+            PatchOp::Replace {
+                path: "/m/a/x".to_string(),
+                value: Value::Int(2),
+            },
+            PatchOp::Add {
+                path: "/m/b".to_string(),
+                value: Value::Bool(true),
+            },
+            PatchOp::Copy {
+                from: "/m/a".to_string(),
+                path: "/m/c".to_string(),
+            },
+            PatchOp::Move {
+                from: "/m/b".to_string(),
+                path: "/moved".to_string(),
+            },
+            PatchOp::Add {
+                path: "/m/c/y".to_string(),
+                value: Value::Null,
+            },
+            PatchOp::Test {
+                path: "/m/c".to_string(),
+                value: object(vec![("x", Value::Int(2)), ("y", Value::Null)]),
+            },
+        ];
+        doc.apply_patch(&ops).unwrap();
+        assert_eq!(
+            doc,
+            object(vec![
+                (
+                    "m",
+                    Value::Map(vec![
+                        (Value::Int(1), s("one")),
+                        (s("a"), object(vec![("x", Value::Int(2))])),
+                        (
+                            s("c"),
+                            object(vec![("x", Value::Int(2)), ("y", Value::Null)])
+                        ),
+                    ])
+                ),
+                ("moved", Value::Bool(true)),
+            ])
+        );
+        assert_eq!(
+            doc.apply_patch(&[PatchOp::Replace {
+                path: "/m/1".to_string(),
+                value: Value::Null,
+            }]),
+            Err(PatchError::PathNotFound("/m/1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_patch_document() {
+        let ops = vec![
+            PatchOp::Copy {
+                from: "/a".to_string(),
+                path: "/b".to_string(),
+            },
+            PatchOp::Test {
+                path: "/b".to_string(),
+                value: Value::Int(1),
+            },
+        ];
+        let doc = ops.into_value();
+        assert_eq!(doc[0]["op"], s("copy"));
+        assert_eq!(doc[0]["from"], s("/a"));
+        assert_eq!(PatchOp::parse_all(&doc).unwrap(), ops);
+        assert_eq!(format!("{}", ops[0]), "copy /a -> /b");
+        assert!(PatchOp::parse(&object(vec![("op", s("add"))])).is_err());
+    }
+}
@@ -1,3 +1,26 @@
+#[macro_use]
+mod macros;
+mod pattern;
+mod sha256;
+
+pub mod canonical;
+pub mod cmp;
+pub mod convert;
+pub mod edit;
+pub mod format;
 pub mod from_value;
+pub mod infer;
 pub mod into_value;
+pub mod json;
+pub mod json_patch;
+pub mod merge_patch;
+pub mod object_map;
+pub mod pointer;
+pub mod query;
+pub mod redact;
+pub mod schema;
+pub mod shared;
+pub mod time;
 pub mod value;
+pub mod value_ref;
+pub mod visit;
@@ -0,0 +1,254 @@
+/// Builds a `Value` from a JSON-like literal.
+///
+/// ```
+/// use value::value;
+///
+/// let tag = "x";
+/// let v = value!({
+///     "name": tag,
+///     "tags": [1, 2, null],
+///     "nested": {"ok": true},
+/// });
+/// assert_eq!(v["tags"][1], value!(2));
+/// ```
+///
+/// Any expression implementing `IntoValue` can be interpolated in value
+/// position. Keys are string literals or any expression that converts into
+/// a `String`; wrap keys that are more than one token in parentheses.
+#[macro_export]
+macro_rules! value {
+    ($($tt:tt)+) => {
+        $crate::value_internal!($($tt)+)
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! value_internal {
+    // Arrays are munched into a list of element expressions.
+
+    (@array [$($elems:expr,)*]) => {
+        vec![$($elems,)*]
+    };
+
+    (@array [$($elems:expr),*]) => {
+        vec![$($elems),*]
+    };
+
+    (@array [$($elems:expr,)*] null $($rest:tt)*) => {
+        $crate::value_internal!(@array [$($elems,)* $crate::value_internal!(null)] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] true $($rest:tt)*) => {
+        $crate::value_internal!(@array [$($elems,)* $crate::value_internal!(true)] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] false $($rest:tt)*) => {
+        $crate::value_internal!(@array [$($elems,)* $crate::value_internal!(false)] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::value_internal!(@array [$($elems,)* $crate::value_internal!([$($array)*])] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] {$($map:tt)*} $($rest:tt)*) => {
+        $crate::value_internal!(@array [$($elems,)* $crate::value_internal!({$($map)*})] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::value_internal!(@array [$($elems,)* $crate::value_internal!($next),] $($rest)*)
+    };
+
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::value_internal!(@array [$($elems,)* $crate::value_internal!($last)])
+    };
+
+    (@array [$($elems:expr),*] , $($rest:tt)*) => {
+        $crate::value_internal!(@array [$($elems,)*] $($rest)*)
+    };
+
+    (@array [$($elems:expr),*] $unexpected:tt $($rest:tt)*) => {
+        $crate::value_unexpected!($unexpected)
+    };
+
+    // Objects are munched key by key into an `ObjectMap` named $object. The
+    // key is collected token by token in the first group, the remaining
+    // input is kept twice so errors can point at the offending token.
+
+    (@object $object:ident () () ()) => {};
+
+    (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        let _ = $object.insert(
+            ::std::convert::Into::<::std::string::String>::into($($key)+),
+            $value,
+        );
+        $crate::value_internal!(@object $object () ($($rest)*) ($($rest)*));
+    };
+
+    (@object $object:ident [$($key:tt)+] ($value:expr) $unexpected:tt $($rest:tt)*) => {
+        $crate::value_unexpected!($unexpected);
+    };
+
+    (@object $object:ident [$($key:tt)+] ($value:expr)) => {
+        let _ = $object.insert(
+            ::std::convert::Into::<::std::string::String>::into($($key)+),
+            $value,
+        );
+    };
+
+    (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
+        $crate::value_internal!(@object $object [$($key)+] ($crate::value_internal!(null)) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: true $($rest:tt)*) $copy:tt) => {
+        $crate::value_internal!(@object $object [$($key)+] ($crate::value_internal!(true)) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: false $($rest:tt)*) $copy:tt) => {
+        $crate::value_internal!(@object $object [$($key)+] ($crate::value_internal!(false)) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: [$($array:tt)*] $($rest:tt)*) $copy:tt) => {
+        $crate::value_internal!(@object $object [$($key)+] ($crate::value_internal!([$($array)*])) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: {$($map:tt)*} $($rest:tt)*) $copy:tt) => {
+        $crate::value_internal!(@object $object [$($key)+] ($crate::value_internal!({$($map)*})) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*) $copy:tt) => {
+        $crate::value_internal!(@object $object [$($key)+] ($crate::value_internal!($value)) , $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: $value:expr) $copy:tt) => {
+        $crate::value_internal!(@object $object [$($key)+] ($crate::value_internal!($value)));
+    };
+
+    // A key without a value.
+    (@object $object:ident ($($key:tt)+) (:) $copy:tt) => {
+        $crate::value_internal!();
+    };
+
+    (@object $object:ident ($($key:tt)+) () $copy:tt) => {
+        $crate::value_internal!();
+    };
+
+    // A colon without a key.
+    (@object $object:ident () (: $($rest:tt)*) ($colon:tt $($copy:tt)*)) => {
+        $crate::value_unexpected!($colon);
+    };
+
+    // A comma inside a key.
+    (@object $object:ident ($($key:tt)*) (, $($rest:tt)*) ($comma:tt $($copy:tt)*)) => {
+        $crate::value_unexpected!($comma);
+    };
+
+    (@object $object:ident () (($key:expr) : $($rest:tt)*) $copy:tt) => {
+        $crate::value_internal!(@object $object ($key) (: $($rest)*) (: $($rest)*));
+    };
+
+    (@object $object:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
+        $crate::value_internal!(@object $object ($($key)* $tt) ($($rest)*) ($($rest)*));
+    };
+
+    // Entry points.
+
+    (null) => {
+        $crate::value::Value::Null
+    };
+
+    (true) => {
+        $crate::value::Value::Bool(true)
+    };
+
+    (false) => {
+        $crate::value::Value::Bool(false)
+    };
+
+    ([]) => {
+        $crate::value::Value::Array(vec![])
+    };
+
+    ([ $($tt:tt)+ ]) => {
+        $crate::value::Value::Array($crate::value_internal!(@array [] $($tt)+))
+    };
+
+    ({}) => {
+        $crate::value::Value::Object($crate::object_map::ObjectMap::new())
+    };
+
+    ({ $($tt:tt)+ }) => {
+        $crate::value::Value::Object({
+            let mut object = $crate::object_map::ObjectMap::new();
+            $crate::value_internal!(@object object () ($($tt)+) ($($tt)+));
+            object
+        })
+    };
+
+    ($other:expr) => {
+        $crate::into_value::IntoValue::into_value(&$other)
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! value_unexpected {
+    () => {};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_map::ObjectMap;
+    use crate::value::Value;
+
+    #[test]
+    fn test_value_macro_literals() {
+        assert_eq!(value!(null), Value::Null);
+        assert_eq!(value!(true), Value::Bool(true));
+        assert_eq!(value!(1), Value::Int(1));
+        assert_eq!(value!(-1.5), Value::F64(-1.5));
+        assert_eq!(value!("x"), Value::String("x".to_string()));
+        assert_eq!(value!([]), Value::Array(vec![]));
+        assert_eq!(value!({}), Value::Object(ObjectMap::new()));
+    }
+
+    #[test]
+    fn test_value_macro_nested() {
+        let v = value!({"name": "x", "tags": [1, 2, null], "nested": {"ok": true}});
+
+        let mut nested = ObjectMap::new();
+        nested.insert("ok".to_string(), Value::Bool(true));
+        let mut expected = ObjectMap::new();
+        expected.insert("name".to_string(), Value::String("x".to_string()));
+        expected.insert(
+            "tags".to_string(),
+            Value::Array(vec![Value::Int(1), Value::Int(2), Value::Null]),
+        );
+        expected.insert("nested".to_string(), Value::Object(nested));
+        assert_eq!(v, Value::Object(expected));
+
+        let keys: Vec<&String> = match &v {
+            Value::Object(o) => o.keys().collect(),
+            _ => unreachable!(),
+        };
+        assert_eq!(keys, vec!["name", "tags", "nested"]);
+    }
+
+    #[test]
+    fn test_value_macro_interpolation() {
+        let name = String::from("alice");
+        let scores: Vec<u8> = vec![3, 4];
+        let key = "dynamic";
+        let v = value!({
+            "name": name,
+            "scores": scores,
+            "sum": 3 + 4,
+            (key): [name.len(), {"inner": -1},],
+        });
+        assert_eq!(v["name"], Value::String("alice".to_string()));
+        assert_eq!(v["scores"], value!([3, 4]));
+        assert_eq!(v["sum"], Value::Int(7));
+        assert_eq!(v["dynamic"][0], Value::UInt(5));
+        assert_eq!(v["dynamic"][1]["inner"], Value::Int(-1));
+    }
+}
@@ -0,0 +1,200 @@
+//! JSON Merge Patch (RFC 7386) on `Value` trees.
+//!
+//! A patch is itself a value: object members are merged recursively, `Null`
+//! deletes a member and anything else replaces the target. Because `Null`
+//! means "delete", a merge patch cannot set a member to `Null`.
+
+use crate::object_map::ObjectMap;
+use crate::value::Value;
+
+impl Value {
+    /// Applies `patch` to this value in place, as described in RFC 7386.
+    pub fn merge_patch(&mut self, patch: &Value) {
+        let members = match patch {
+            Value::Object(members) => members,
+            _ => {
+                *self = patch.clone();
+                return;
+            }
+        };
+
+        if !matches!(self, Value::Object(_)) {
+            *self = Value::Object(ObjectMap::new());
+        }
+        if let Value::Object(target) = self {
+            for (key, value) in members {
+                if let Value::Null = value {
+                    target.remove(key);
+                } else {
+                    target
+                        .entry(key.clone())
+                        .or_insert(Value::Null)
+                        .merge_patch(value);
+                }
+            }
+        }
+    }
+
+    /// Builds the smallest merge patch that turns `from` into `to`.
+    ///
+    /// Members missing from `to` become `Null`, changed members are diffed
+    /// recursively when both sides are objects and replaced otherwise. Members
+    /// of `to` that are `Null` cannot be expressed and end up removed.
+    pub fn merge_patch_diff(from: &Value, to: &Value) -> Value {
+        let (a, b) = match (from, to) {
+            (Value::Object(a), Value::Object(b)) => (a, b),
+            _ => return to.clone(),
+        };
+
+        let mut patch = ObjectMap::new();
+        for (key, _) in a {
+            if !b.contains_key(key) {
+                patch.insert(key.clone(), Value::Null);
+            }
+        }
+        for (key, new) in b {
+            match a.get(key) {
+                Some(old) if old == new => {}
+                Some(old) => {
+                    patch.insert(key.clone(), Value::merge_patch_diff(old, new));
+                }
+                None => {
+                    patch.insert(key.clone(), Value::merge_patch_diff(&Value::Null, new));
+                }
+            }
+        }
+        Value::Object(patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(entries: Vec<(&str, Value)>) -> Value {
+        Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_string())
+    }
+
+    #[test]
+    fn test_merge_patch_rfc_examples() {
+        let cases = vec![
+            (
+                object(vec![("a", s("b"))]),
+                object(vec![("a", s("c"))]),
+                object(vec![("a", s("c"))]),
+            ),
+            (
+                object(vec![("a", s("b"))]),
+                object(vec![("b", s("c"))]),
+                object(vec![("a", s("b")), ("b", s("c"))]),
+            ),
+            (
+                object(vec![("a", s("b")), ("b", s("c"))]),
+                object(vec![("a", Value::Null)]),
+                object(vec![("b", s("c"))]),
+            ),
+            (
+                object(vec![("a", Value::Array(vec![s("b")]))]),
+                object(vec![("a", s("c"))]),
+                object(vec![("a", s("c"))]),
+            ),
+            (
+                object(vec![("a", s("c"))]),
+                object(vec![("a", Value::Array(vec![s("b")]))]),
+                object(vec![("a", Value::Array(vec![s("b")]))]),
+            ),
+            (
+                object(vec![("a", object(vec![("b", s("c"))]))]),
+                object(vec![("a", object(vec![("b", s("d")), ("c", Value::Null)]))]),
+                object(vec![("a", object(vec![("b", s("d"))]))]),
+            ),
+            (
+                Value::Array(vec![s("a"), s("b")]),
+                Value::Array(vec![s("c"), s("d")]),
+                Value::Array(vec![s("c"), s("d")]),
+            ),
+            (
+                object(vec![("a", s("b"))]),
+                Value::Array(vec![s("c")]),
+                Value::Array(vec![s("c")]),
+            ),
+            (object(vec![("a", s("foo"))]), Value::Null, Value::Null),
+            (object(vec![("a", s("foo"))]), s("bar"), s("bar")),
+            (
+                object(vec![("e", Value::Null)]),
+                object(vec![("a", Value::Int(1))]),
+                object(vec![("e", Value::Null), ("a", Value::Int(1))]),
+            ),
+            (
+                Value::Array(vec![Value::Int(1), Value::Int(2)]),
+                object(vec![("a", s("b")), ("c", Value::Null)]),
+                object(vec![("a", s("b"))]),
+            ),
+            (
+                object(vec![]),
+                object(vec![(
+                    "a",
+                    object(vec![("bb", object(vec![("ccc", Value::Null)]))]),
+                )]),
+                object(vec![("a", object(vec![("bb", object(vec![]))]))]),
+            ),
+        ];
+        for (target, patch, expected) in cases {
+            let mut result = target.clone();
+            result.merge_patch(&patch);
+            assert_eq!(result, expected, "target {:?} patch {:?}", target, patch);
+        }
+    }
+
+    #[test]
+    fn test_merge_patch_diff() {
+        let from = object(vec![
+            ("keep", Value::Int(1)),
+            ("drop", Value::Int(2)),
+            (
+                "nested",
+                object(vec![("x", Value::Int(1)), ("y", Value::Int(2))]),
+            ),
+            ("scalar", s("a")),
+        ]);
+        let to = object(vec![
+            ("keep", Value::Int(1)),
+            (
+                "nested",
+                object(vec![("x", Value::Int(1)), ("y", Value::Int(3))]),
+            ),
+            ("scalar", object(vec![("z", Value::Bool(true))])),
+            ("added", Value::Array(vec![])),
+        ]);
+
+        let patch = Value::merge_patch_diff(&from, &to);
+        assert_eq!(
+            patch,
+            object(vec![
+                ("drop", Value::Null),
+                ("nested", object(vec![("y", Value::Int(3))])),
+                ("scalar", object(vec![("z", Value::Bool(true))])),
+                ("added", Value::Array(vec![])),
+            ])
+        );
+
+        let mut applied = from.clone();
+        applied.merge_patch(&patch);
+        assert_eq!(applied, to);
+
+        assert_eq!(Value::merge_patch_diff(&from, &from), object(vec![]));
+        assert_eq!(
+            Value::merge_patch_diff(&Value::Int(1), &Value::Int(2)),
+            Value::Int(2)
+        );
+    }
+}
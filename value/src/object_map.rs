@@ -0,0 +1,383 @@
+use crate::value::Value;
+use std::collections::HashMap;
+use std::iter::FromIterator;
+
+/// String keyed map that remembers insertion order.
+///
+/// Entries live in a vector and a hash index points from key to position, so
+/// iteration, encoding and formatting all follow the order keys were first
+/// inserted in. Overwriting a key keeps its position, removing a key shifts
+/// the following entries down.
+#[derive(Debug, Default, Clone)]
+pub struct ObjectMap {
+    entries: Vec<(String, Value)>,
+    index: HashMap<String, usize>,
+}
+
+impl ObjectMap {
+    pub fn new() -> Self {
+        ObjectMap {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    pub fn with_capacity(n: usize) -> Self {
+        ObjectMap {
+            entries: Vec::with_capacity(n),
+            index: HashMap::with_capacity(n),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let i = *self.index.get(key)?;
+        Some(&self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        let i = *self.index.get(key)?;
+        Some(&mut self.entries[i].1)
+    }
+
+    /// Position of `key` in iteration order.
+    pub fn get_index_of(&self, key: &str) -> Option<usize> {
+        self.index.get(key).cloned()
+    }
+
+    pub fn get_index(&self, i: usize) -> Option<(&String, &Value)> {
+        self.entries.get(i).map(|(k, v)| (k, v))
+    }
+
+    /// Inserts `value` under `key`. An existing key keeps its position and
+    /// the old value is returned; a new key is appended at the end.
+    pub fn insert(&mut self, key: String, value: Value) -> Option<Value> {
+        if let Some(&i) = self.index.get(&key) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+    }
+
+    /// Removes `key` and keeps the relative order of the remaining entries.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    pub fn remove_entry(&mut self, key: &str) -> Option<(String, Value)> {
+        let i = self.index.remove(key)?;
+        let entry = self.entries.remove(i);
+        for (k, _) in &self.entries[i..] {
+            if let Some(pos) = self.index.get_mut(k) {
+                *pos -= 1;
+            }
+        }
+        Some(entry)
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            inner: self.entries.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut {
+            inner: self.entries.iter_mut(),
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+
+    /// Gets the entry for `key` for in-place manipulation.
+    pub fn entry(&mut self, key: String) -> Entry<'_> {
+        match self.index.get(&key) {
+            Some(&i) => Entry::Occupied(OccupiedEntry {
+                map: self,
+                index: i,
+            }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    /// Sorts the entries by key, used for deterministic output.
+    pub fn sort_keys(&mut self) {
+        self.entries.sort_by(|a, b| a.0.cmp(&b.0));
+        self.reindex();
+    }
+
+    fn reindex(&mut self) {
+        self.index.clear();
+        for (i, (k, _)) in self.entries.iter().enumerate() {
+            self.index.insert(k.clone(), i);
+        }
+    }
+}
+
+pub enum Entry<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a>),
+}
+
+pub struct OccupiedEntry<'a> {
+    map: &'a mut ObjectMap,
+    index: usize,
+}
+
+pub struct VacantEntry<'a> {
+    map: &'a mut ObjectMap,
+    key: String,
+}
+
+impl<'a> Entry<'a> {
+    pub fn key(&self) -> &str {
+        match self {
+            Entry::Occupied(e) => e.key(),
+            Entry::Vacant(e) => &e.key,
+        }
+    }
+
+    pub fn or_insert(self, default: Value) -> &'a mut Value {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> Value>(self, default: F) -> &'a mut Value {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut Value {
+        self.or_insert_with(Value::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut Value)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut e) => {
+                f(e.get_mut());
+                Entry::Occupied(e)
+            }
+            Entry::Vacant(e) => Entry::Vacant(e),
+        }
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    pub fn key(&self) -> &str {
+        &self.map.entries[self.index].0
+    }
+
+    pub fn get(&self) -> &Value {
+        &self.map.entries[self.index].1
+    }
+
+    pub fn get_mut(&mut self) -> &mut Value {
+        &mut self.map.entries[self.index].1
+    }
+
+    pub fn into_mut(self) -> &'a mut Value {
+        &mut self.map.entries[self.index].1
+    }
+
+    pub fn insert(&mut self, value: Value) -> Value {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> Value {
+        let key = self.map.entries[self.index].0.clone();
+        self.map.remove(&key).unwrap_or_default()
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn insert(self, value: Value) -> &'a mut Value {
+        let i = self.map.entries.len();
+        self.map.insert(self.key, value);
+        &mut self.map.entries[i].1
+    }
+}
+
+pub struct Iter<'a> {
+    inner: std::slice::Iter<'a, (String, Value)>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a String, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Iter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, v)| (k, v))
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+pub struct IterMut<'a> {
+    inner: std::slice::IterMut<'a, (String, Value)>,
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a String, &'a mut Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (&*k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for IterMut<'a> {}
+
+impl<'a> IntoIterator for &'a ObjectMap {
+    type Item = (&'a String, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut ObjectMap {
+    type Item = (&'a String, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl IntoIterator for ObjectMap {
+    type Item = (String, Value);
+    type IntoIter = std::vec::IntoIter<(String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl FromIterator<(String, Value)> for ObjectMap {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        let mut result = ObjectMap::new();
+        result.extend(iter);
+        result
+    }
+}
+
+impl Extend<(String, Value)> for ObjectMap {
+    fn extend<I: IntoIterator<Item = (String, Value)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(m: &ObjectMap) -> Vec<&str> {
+        m.keys().map(|k| k.as_str()).collect()
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut m = ObjectMap::new();
+        m.insert("b".to_string(), Value::Int(1));
+        m.insert("a".to_string(), Value::Int(2));
+        m.insert("c".to_string(), Value::Int(3));
+        assert_eq!(keys(&m), vec!["b", "a", "c"]);
+
+        let old = m.insert("a".to_string(), Value::Int(4));
+        assert!(matches!(old, Some(Value::Int(2))));
+        assert_eq!(keys(&m), vec!["b", "a", "c"]);
+        assert!(matches!(m.get("a"), Some(Value::Int(4))));
+    }
+
+    #[test]
+    fn test_remove_keeps_order() {
+        let mut m: ObjectMap = vec![
+            ("x".to_string(), Value::Int(1)),
+            ("y".to_string(), Value::Int(2)),
+            ("z".to_string(), Value::Int(3)),
+        ]
+        .into_iter()
+        .collect();
+        assert!(matches!(m.remove("x"), Some(Value::Int(1))));
+        assert_eq!(keys(&m), vec!["y", "z"]);
+        assert_eq!(m.get_index_of("z"), Some(1));
+        assert!(matches!(m.get("z"), Some(Value::Int(3))));
+        assert!(m.remove("x").is_none());
+
+        m.insert("x".to_string(), Value::Null);
+        assert_eq!(keys(&m), vec!["y", "z", "x"]);
+
+        m.sort_keys();
+        assert_eq!(keys(&m), vec!["x", "y", "z"]);
+        assert_eq!(m.get_index_of("z"), Some(2));
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut m = ObjectMap::new();
+        m.entry("n".to_string()).or_insert(Value::Int(0));
+        m.entry("n".to_string()).and_modify(|v| {
+            if let Value::Int(n) = v {
+                *n += 1;
+            }
+        });
+        assert!(matches!(m.get("n"), Some(Value::Int(1))));
+
+        match m.entry("n".to_string()) {
+            Entry::Occupied(e) => assert!(matches!(e.remove(), Value::Int(1))),
+            Entry::Vacant(_) => panic!("expected occupied entry"),
+        }
+        assert!(m.is_empty());
+        assert!(matches!(m.entry("x".to_string()).or_default(), Value::Null));
+    }
+}
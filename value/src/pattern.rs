@@ -0,0 +1,561 @@
+//! A small regular expression engine for schema `pattern`s.
+//!
+//! Supports the common subset of ECMA-262 syntax: literals, `.`, `^`, `$`,
+//! character classes with ranges and negation, `\d \w \s` and their
+//! negations, groups (`(...)` and `(?:...)`), alternation and the greedy and
+//! lazy quantifiers `* + ? {n} {n,} {n,m}`. Backreferences, lookaround and
+//! named groups are rejected when compiling.
+//!
+//! Patterns compile to an NFA that is run as a Thompson simulation, tracking
+//! every live state at once. Matching never backtracks or recurses, so it
+//! takes time linear in the input whatever the pattern, which matters as
+//! schemas validate untrusted values. Counted repeats are expanded when
+//! compiling, and patterns that expand past `MAX_PROGRAM` instructions or
+//! repeat counts above `MAX_REPEAT` are rejected.
+
+/// Upper bound on the size of a compiled pattern, and on the number of
+/// repeat iterations expanded while compiling it.
+const MAX_PROGRAM: usize = 10_000;
+
+/// Upper bound on the counts in `{n}`, `{n,}` and `{n,m}`.
+const MAX_REPEAT: usize = 1_000;
+
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    program: Vec<Inst>,
+}
+
+// One NFA state. `Char`, `Any` and `Class` consume a character, the others
+// are followed without consuming input.
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    // Laziness does not change whether a pattern matches, so it is not kept.
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Range(lo, hi) => lo <= c && c <= hi,
+            ClassItem::Digit(want) => c.is_ascii_digit() == want,
+            ClassItem::Word(want) => (c.is_ascii_alphanumeric() || c == '_') == want,
+            ClassItem::Space(want) => c.is_whitespace() == want,
+        }
+    }
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        self.items.iter().any(|item| item.matches(c)) != self.negated
+    }
+}
+
+impl Pattern {
+    /// Compiles `source`, or describes why it is not supported.
+    pub fn new(source: &str) -> Result<Pattern, String> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+        };
+        let alternatives = parser.parse_alternatives()?;
+        if parser.pos < parser.chars.len() {
+            return Err(format!("unmatched ')' at {}", parser.pos));
+        }
+        let mut compiler = Compiler {
+            program: Vec::new(),
+            iterations: 0,
+        };
+        compiler.alternatives(&alternatives)?;
+        compiler.emit(Inst::Match)?;
+        Ok(Pattern {
+            program: compiler.program,
+        })
+    }
+
+    /// Whether the pattern matches anywhere in `s`.
+    pub fn is_match(&self, s: &str) -> bool {
+        let text: Vec<char> = s.chars().collect();
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut stack = Vec::new();
+        for pos in 0..=text.len() {
+            // A fresh thread at every position makes the search unanchored.
+            if self.add(&mut current, &mut stack, 0, pos, text.len()) {
+                return true;
+            }
+            let c = match text.get(pos) {
+                Some(c) => *c,
+                None => break,
+            };
+            next.clear();
+            for &pc in &current.list {
+                let step = match &self.program[pc] {
+                    Inst::Char(want) => *want == c,
+                    Inst::Any => c != '\n',
+                    Inst::Class(class) => class.matches(c),
+                    _ => false,
+                };
+                if step && self.add(&mut next, &mut stack, pc + 1, pos + 1, text.len()) {
+                    return true;
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+        }
+        false
+    }
+
+    // Adds `pc` and every state reachable from it without consuming input
+    // at `pos`. Returns true once `Match` is reached.
+    fn add(
+        &self,
+        threads: &mut Threads,
+        stack: &mut Vec<usize>,
+        pc: usize,
+        pos: usize,
+        len: usize,
+    ) -> bool {
+        stack.clear();
+        stack.push(pc);
+        while let Some(pc) = stack.pop() {
+            if !threads.insert(pc) {
+                continue;
+            }
+            match self.program[pc] {
+                Inst::Split(a, b) => {
+                    stack.push(b);
+                    stack.push(a);
+                }
+                Inst::Jump(a) => stack.push(a),
+                Inst::Start if pos == 0 => stack.push(pc + 1),
+                Inst::End if pos == len => stack.push(pc + 1),
+                Inst::Match => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+}
+
+// The states live at one position, in the order they were added.
+struct Threads {
+    list: Vec<usize>,
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Threads {
+        Threads {
+            list: Vec::new(),
+            seen: vec![false; len],
+        }
+    }
+
+    fn insert(&mut self, pc: usize) -> bool {
+        if self.seen[pc] {
+            return false;
+        }
+        self.seen[pc] = true;
+        self.list.push(pc);
+        true
+    }
+
+    fn clear(&mut self) {
+        for pc in self.list.drain(..) {
+            self.seen[pc] = false;
+        }
+    }
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+    // Repeat iterations expanded so far. Zero-width nodes such as `(?:)`
+    // emit nothing, so the program size alone does not bound the work.
+    iterations: usize,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> Result<usize, String> {
+        if self.program.len() >= MAX_PROGRAM {
+            return Err("pattern too large".to_string());
+        }
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    fn iteration(&mut self) -> Result<(), String> {
+        self.iterations += 1;
+        if self.iterations > MAX_PROGRAM {
+            return Err("pattern too large".to_string());
+        }
+        Ok(())
+    }
+
+    fn alternatives(&mut self, alternatives: &[Vec<Node>]) -> Result<(), String> {
+        let mut jumps = Vec::new();
+        let (last, rest) = alternatives.split_last().expect("at least one alternative");
+        for seq in rest {
+            let split = self.emit(Inst::Split(0, 0))?;
+            self.sequence(seq)?;
+            jumps.push(self.emit(Inst::Jump(0))?);
+            self.program[split] = Inst::Split(split + 1, self.program.len());
+        }
+        self.sequence(last)?;
+        let end = self.program.len();
+        for jump in jumps {
+            self.program[jump] = Inst::Jump(end);
+        }
+        Ok(())
+    }
+
+    fn sequence(&mut self, nodes: &[Node]) -> Result<(), String> {
+        for node in nodes {
+            self.node(node)?;
+        }
+        Ok(())
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Char(c) => self.emit(Inst::Char(*c)).map(|_| ()),
+            Node::Any => self.emit(Inst::Any).map(|_| ()),
+            Node::Class(class) => self.emit(Inst::Class(class.clone())).map(|_| ()),
+            Node::Start => self.emit(Inst::Start).map(|_| ()),
+            Node::End => self.emit(Inst::End).map(|_| ()),
+            Node::Group(alternatives) => self.alternatives(alternatives),
+            Node::Repeat { node, min, max } => {
+                for _ in 0..*min {
+                    self.iteration()?;
+                    self.node(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.node(node)?;
+                        self.emit(Inst::Jump(split))?;
+                        self.program[split] = Inst::Split(split + 1, self.program.len());
+                    }
+                    Some(max) => {
+                        for _ in *min..*max {
+                            self.iteration()?;
+                            let split = self.emit(Inst::Split(0, 0))?;
+                            self.node(node)?;
+                            self.program[split] = Inst::Split(split + 1, self.program.len());
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn parse_alternatives(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.parse_sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alternatives.push(self.parse_sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn parse_sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom)?);
+        }
+        Ok(nodes)
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        let start = self.pos;
+        match self.next() {
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Start),
+            Some('$') => Ok(Node::End),
+            Some('(') => {
+                if self.peek() == Some('?') {
+                    self.pos += 1;
+                    if self.next() != Some(':') {
+                        return Err(format!("unsupported group at {}", start));
+                    }
+                }
+                let alternatives = self.parse_alternatives()?;
+                if self.next() != Some(')') {
+                    return Err(format!("unclosed group at {}", start));
+                }
+                Ok(Node::Group(alternatives))
+            }
+            Some('[') => self.parse_class(start),
+            Some('\\') => match self.parse_escape(start)? {
+                ClassItem::Range(c, _) => Ok(Node::Char(c)),
+                item => Ok(Node::Class(Class {
+                    negated: false,
+                    items: vec![item],
+                })),
+            },
+            Some(c @ '*') | Some(c @ '+') | Some(c @ '?') => {
+                Err(format!("nothing to repeat before '{}' at {}", c, start))
+            }
+            Some(c) => Ok(Node::Char(c)),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+
+    // Escapes shared by atoms and classes. A plain character comes back as a
+    // one-character range.
+    fn parse_escape(&mut self, start: usize) -> Result<ClassItem, String> {
+        let item = match self.next() {
+            Some('d') => ClassItem::Digit(true),
+            Some('D') => ClassItem::Digit(false),
+            Some('w') => ClassItem::Word(true),
+            Some('W') => ClassItem::Word(false),
+            Some('s') => ClassItem::Space(true),
+            Some('S') => ClassItem::Space(false),
+            Some('n') => ClassItem::Range('\n', '\n'),
+            Some('r') => ClassItem::Range('\r', '\r'),
+            Some('t') => ClassItem::Range('\t', '\t'),
+            Some(c) if !c.is_ascii_alphanumeric() => ClassItem::Range(c, c),
+            Some(c) => return Err(format!("unsupported escape '\\{}' at {}", c, start)),
+            None => return Err("unexpected end".to_string()),
+        };
+        Ok(item)
+    }
+
+    fn parse_class(&mut self, start: usize) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut items = Vec::new();
+        loop {
+            let item = match self.next() {
+                Some(']') if !items.is_empty() => break,
+                Some('\\') => self.parse_escape(self.pos - 1)?,
+                Some(c) => ClassItem::Range(c, c),
+                None => return Err(format!("unclosed class at {}", start)),
+            };
+            let item = match item {
+                ClassItem::Range(lo, _)
+                    if self.peek() == Some('-')
+                        && self.chars.get(self.pos + 1).is_some_and(|c| *c != ']') =>
+                {
+                    self.pos += 1;
+                    let hi = match self.next() {
+                        Some('\\') => match self.parse_escape(self.pos - 1)? {
+                            ClassItem::Range(hi, _) => hi,
+                            _ => return Err(format!("invalid range at {}", start)),
+                        },
+                        Some(hi) => hi,
+                        None => return Err(format!("unclosed class at {}", start)),
+                    };
+                    if hi < lo {
+                        return Err(format!("invalid range at {}", start));
+                    }
+                    ClassItem::Range(lo, hi)
+                }
+                item => item,
+            };
+            items.push(item);
+        }
+        Ok(Node::Class(Class { negated, items }))
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let start = self.pos;
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.parse_braces() {
+                Some(bounds) => bounds,
+                // Not a quantifier: `{` is taken literally, as in ECMA-262.
+                None => return Ok(atom),
+            },
+            _ => return Ok(atom),
+        };
+        // Step over the quantifier character or the closing brace.
+        self.pos += 1;
+        if min > MAX_REPEAT || max.is_some_and(|max| max > MAX_REPEAT) {
+            return Err(format!("repetition count too large at {}", start));
+        }
+        if let Some(max) = max {
+            if max < min {
+                return Err(format!("invalid repetition at {}", start));
+            }
+        }
+        if let Node::Start | Node::End = atom {
+            return Err(format!("nothing to repeat at {}", start));
+        }
+        // Lazy quantifiers match the same strings as greedy ones.
+        if self.peek() == Some('?') {
+            self.pos += 1;
+        }
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+        })
+    }
+
+    // Parses `{n}`, `{n,}` or `{n,m}` and leaves the position on the `}`.
+    fn parse_braces(&mut self) -> Option<(usize, Option<usize>)> {
+        let rest: String = self.chars[self.pos + 1..].iter().collect();
+        let end = rest.find('}')?;
+        let body = &rest[..end];
+        let (min, max) = match body.find(',') {
+            Some(i) => {
+                let max = &body[i + 1..];
+                let max = if max.is_empty() {
+                    None
+                } else {
+                    Some(max.parse().ok()?)
+                };
+                (body[..i].parse().ok()?, max)
+            }
+            None => {
+                let n = body.parse().ok()?;
+                (n, Some(n))
+            }
+        };
+        self.pos += 1 + body.chars().count();
+        Some((min, max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, s: &str) -> bool {
+        Pattern::new(pattern).unwrap().is_match(s)
+    }
+
+    #[test]
+    fn test_match() {
+        assert!(matches("abc", "xxabcxx"));
+        assert!(!matches("^abc$", "xabc"));
+        assert!(matches("^a.c$", "abc"));
+        assert!(matches("^[a-z]+@[a-z]+\\.com$", "me@example.com"));
+        assert!(!matches("^[a-z]+@[a-z]+\\.com$", "Me@example.com"));
+        assert!(matches("^[^0-9]*$", "abc"));
+        assert!(!matches("^[^0-9]*$", "a1c"));
+        assert!(matches("^\\d{3}-\\d{4}$", "555-1234"));
+        assert!(!matches("^\\d{3}-\\d{4}$", "55-1234"));
+        assert!(matches("^(cat|dog)s?$", "dogs"));
+        assert!(!matches("^(cat|dog)s?$", "cow"));
+        assert!(matches("^(?:ab)+$", "ababab"));
+        assert!(matches("^a{2,}$", "aaaa"));
+        assert!(!matches("^a{2,3}$", "aaaa"));
+        assert!(matches("^\\w+\\s\\w+$", "hello world"));
+        assert!(matches("^(a*)*b$", "aaab"));
+        assert!(matches("^a+?b$", "aaab"));
+        assert!(matches("x{y", "x{y"));
+        assert!(matches("^[-a]+$", "-a-"));
+        assert!(matches("^é+$", "éé"));
+        assert!(matches("", "anything"));
+    }
+
+    #[test]
+    fn test_long_input() {
+        let long = "a".repeat(200_000);
+        assert!(matches("^[a-z]+$", &long));
+        assert!(!matches("^[a-z]+$", &(long.clone() + "1")));
+        assert!(matches("^(?:a|b)*$", &long));
+        assert!(matches("a$", &long));
+        assert!(!matches("b", &long));
+    }
+
+    #[test]
+    fn test_nested_quantifiers() {
+        let text = "a".repeat(5_000) + "c";
+        assert!(!matches("^(a*)*b$", &text));
+        assert!(!matches("^(a|aa)+b$", &text));
+        assert!(!matches("^(a+)+$", &text));
+        assert!(!matches("^(?:a?){30}a{30}$", &"a".repeat(29)));
+        assert!(matches("^(?:a?){30}a{30}$", &"a".repeat(30)));
+        assert!(matches("^(a*)*c$", &text));
+    }
+
+    #[test]
+    fn test_invalid() {
+        for bad in &["(", "a)", "[a", "*a", "a{3,1}", "\\1", "(?=a)", "[z-a]"] {
+            assert!(Pattern::new(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(
+            Pattern::new("(?:a{1000}){1000}").unwrap_err(),
+            "pattern too large"
+        );
+        assert!(Pattern::new("a{4000000000}").is_err());
+    }
+
+    #[test]
+    fn test_zero_width_repeats() {
+        assert_eq!(
+            Pattern::new("(?:){18446744073709551615}").unwrap_err(),
+            "repetition count too large at 4"
+        );
+        assert!(Pattern::new("(){1001}").is_err());
+        assert!(matches("^(?:){1000}a$", "a"));
+        assert_eq!(
+            Pattern::new("((?:){1000}){1000}").unwrap_err(),
+            "pattern too large"
+        );
+        assert!(matches("^(?:^){1000}a(?:$){1000}", "a"));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::value::Value;
+use std::ops::Index;
+
+static NULL: Value = Value::Null;
+
+impl Index<&str> for Value {
+    type Output = Value;
+
+    /// Looks up `key` in an object, or in a map under a string key. Anything
+    /// missing, including indexing a non-object, yields `Value::Null`.
+    fn index(&self, key: &str) -> &Value {
+        match self {
+            Value::Object(o) => o.get(key).unwrap_or(&NULL),
+            Value::Map(pairs) => match map_key_position(pairs, key) {
+                Some(i) => &pairs[i].1,
+                None => &NULL,
+            },
+            _ => &NULL,
+        }
+    }
+}
+
+impl Index<usize> for Value {
+    type Output = Value;
+
+    /// Looks up position `i` in an array. Out of range and non-array values
+    /// yield `Value::Null`.
+    fn index(&self, i: usize) -> &Value {
+        match self {
+            Value::Array(arr) => arr.get(i).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
+
+/// Position of the first map entry whose key is the string `key`. Keys and
+/// pointer tokens address these entries the same way as object members.
+pub(crate) fn map_key_position(pairs: &[(Value, Value)], key: &str) -> Option<usize> {
+    pairs
+        .iter()
+        .position(|(k, _)| matches!(k, Value::String(s) if s == key))
+}
+
+/// Splits a JSON Pointer (RFC 6901) into unescaped reference tokens.
+///
+/// The empty pointer refers to the whole document and gives no tokens. Any
+/// other pointer has to start with `/`.
+pub fn parse_pointer(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return None;
+    }
+    let tokens = pointer[1..]
+        .split('/')
+        .map(|t| t.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    Some(tokens)
+}
+
+/// Escapes a single reference token so it can be joined into a pointer.
+pub fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Parses an array index token. Leading zeros and signs are rejected as
+/// RFC 6901 requires.
+pub fn parse_array_index(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    if !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+impl Value {
+    /// Resolves a JSON Pointer such as `/a/0/b` against this value.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        let tokens = parse_pointer(pointer)?;
+        let mut target = self;
+        for token in &tokens {
+            target = target.child(token)?;
+        }
+        Some(target)
+    }
+
+    /// Mutable counterpart of [`Value::pointer`].
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        let tokens = parse_pointer(pointer)?;
+        let mut target = self;
+        for token in &tokens {
+            target = target.child_mut(token)?;
+        }
+        Some(target)
+    }
+
+    pub(crate) fn child(&self, token: &str) -> Option<&Value> {
+        match self {
+            Value::Object(o) => o.get(token),
+            Value::Array(arr) => arr.get(parse_array_index(token)?),
+            Value::Map(pairs) => Some(&pairs[map_key_position(pairs, token)?].1),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn child_mut(&mut self, token: &str) -> Option<&mut Value> {
+        match self {
+            Value::Object(o) => o.get_mut(token),
+            Value::Array(arr) => arr.get_mut(parse_array_index(token)?),
+            Value::Map(pairs) => {
+                let i = map_key_position(pairs, token)?;
+                Some(&mut pairs[i].1)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object_map::ObjectMap;
+
+    fn sample() -> Value {
+        let mut inner = ObjectMap::new();
+        inner.insert("b".to_string(), Value::Int(1));
+        inner.insert("c/d".to_string(), Value::Bool(true));
+        inner.insert("e~f".to_string(), Value::Bool(false));
+        let mut outer = ObjectMap::new();
+        outer.insert(
+            "a".to_string(),
+            Value::Array(vec![Value::Object(inner), Value::Null]),
+        );
+        Value::Object(outer)
+    }
+
+    #[test]
+    fn test_index() {
+        let v = sample();
+        assert!(matches!(v["a"][0]["b"], Value::Int(1)));
+        assert!(matches!(v["a"][5]["b"], Value::Null));
+        assert!(matches!(v["missing"]["deeper"][3], Value::Null));
+        assert!(matches!(Value::Int(3)["x"], Value::Null));
+    }
+
+    #[test]
+    fn test_pointer() {
+        let mut v = sample();
+        assert!(matches!(v.pointer(""), Some(Value::Object(_))));
+        assert!(matches!(v.pointer("/a/0/b"), Some(Value::Int(1))));
+        assert!(matches!(v.pointer("/a/0/c~1d"), Some(Value::Bool(true))));
+        assert!(matches!(v.pointer("/a/0/e~0f"), Some(Value::Bool(false))));
+        assert!(matches!(v.pointer("/a/1"), Some(Value::Null)));
+        assert!(v.pointer("/a/01").is_none());
+        assert!(v.pointer("/a/2").is_none());
+        assert!(v.pointer("a").is_none());
+        assert!(v.pointer("/a/0/b/x").is_none());
+
+        *v.pointer_mut("/a/0/b").unwrap() = Value::String("x".to_string());
+        assert!(matches!(v.pointer("/a/0/b"), Some(Value::String(s)) if s == "x"));
+    }
+
+    #[test]
+    fn test_escape_pointer_token() {
+        assert_eq!(escape_pointer_token("a/b~c"), "a~1b~0c");
+        assert_eq!(parse_pointer("/a~1b~0c").unwrap(), vec!["a/b~c"]);
+    }
+}
@@ -0,0 +1,780 @@
+//! JSONPath queries over `Value` trees.
+//!
+//! The syntax follows RFC 9535:
+//!
+//! - `$` is the root, `.name` or `['name']` selects a member, `[0]` and
+//!   `[-1]` select array elements, `[start:end:step]` slices, `*` selects
+//!   every child and `..` applies the next selector to a node and all of its
+//!   descendants. Several selectors can share a bracket: `['a','b']`, `[0,2]`.
+//! - `[?expr]` or `[?(expr)]` keeps the children for which `expr` holds.
+//!   `expr` compares paths relative to the child (`@.price`) or the root
+//!   (`$.limit`) with literals using `== != < <= > >=`, combines them with
+//!   `&& || !` and parentheses, and treats a bare path as an existence test.
+//!
+//! Children of a `Map` are reachable by wildcard and filters, and by name
+//! when the key is a string. Numbers compare by value, so `1 == 1.0`.
+
+use crate::cmp::compare_numbers;
+use crate::value::Value;
+use crate::visit::{path_to_pointer, PathSegment};
+use std::cmp::Ordering;
+use std::error::Error as StdError;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    InvalidSyntax(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryError::InvalidSyntax(p) => write!(f, "InvalidSyntax: {}", p),
+        }
+    }
+}
+
+impl StdError for QueryError {}
+
+/// A node selected by a query, borrowed from the queried tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'a> {
+    pub path: Vec<PathSegment<'a>>,
+    pub value: &'a Value,
+}
+
+impl<'a> Match<'a> {
+    /// The path of the match as a JSON Pointer.
+    pub fn pointer(&self) -> String {
+        path_to_pointer(&self.path)
+    }
+}
+
+/// A compiled query. Compile once, run against any number of values.
+#[derive(Debug, Clone)]
+pub struct Query {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, i64),
+    Filter(Expr),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CmpOp, Operand),
+    Exists(PathExpr),
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Literal(Value),
+    Path(PathExpr),
+}
+
+#[derive(Debug, Clone)]
+struct PathExpr {
+    absolute: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+type Node<'a> = (Vec<PathSegment<'a>>, &'a Value);
+
+impl Query {
+    pub fn compile(expr: &str) -> Result<Query, QueryError> {
+        let mut parser = Parser {
+            chars: expr.chars().collect(),
+            pos: 0,
+        };
+        parser.expect('$')?;
+        let segments = parser.parse_segments()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unexpected character"));
+        }
+        Ok(Query { segments })
+    }
+
+    /// Every node the query selects, in document order per selector.
+    pub fn find<'a>(&self, root: &'a Value) -> Vec<Match<'a>> {
+        evaluate(&self.segments, root, (Vec::new(), root))
+            .into_iter()
+            .map(|(path, value)| Match { path, value })
+            .collect()
+    }
+}
+
+impl Value {
+    /// Compiles `expr` and runs it against this value.
+    pub fn query(&self, expr: &str) -> Result<Vec<Match<'_>>, QueryError> {
+        Ok(Query::compile(expr)?.find(self))
+    }
+}
+
+fn evaluate<'a>(segments: &[Segment], root: &'a Value, start: Node<'a>) -> Vec<Node<'a>> {
+    let mut nodes = vec![start];
+    for segment in segments {
+        let mut next = Vec::new();
+        for node in nodes {
+            if segment.descendant {
+                let mut all = Vec::new();
+                descendants(node, &mut all);
+                for node in all {
+                    select(&segment.selectors, root, &node, &mut next);
+                }
+            } else {
+                select(&segment.selectors, root, &node, &mut next);
+            }
+        }
+        nodes = next;
+    }
+    nodes
+}
+
+// The node itself followed by all of its descendants, parents first.
+fn descendants<'a>(node: Node<'a>, out: &mut Vec<Node<'a>>) {
+    let children = children(&node);
+    out.push(node);
+    for child in children {
+        descendants(child, out);
+    }
+}
+
+fn child<'a>(node: &Node<'a>, segment: PathSegment<'a>, value: &'a Value) -> Node<'a> {
+    let mut path = node.0.clone();
+    path.push(segment);
+    (path, value)
+}
+
+fn children<'a>(node: &Node<'a>) -> Vec<Node<'a>> {
+    match node.1 {
+        Value::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .map(|(i, v)| child(node, PathSegment::Index(i), v))
+            .collect(),
+        Value::Object(o) => o
+            .iter()
+            .map(|(k, v)| child(node, PathSegment::Key(k), v))
+            .collect(),
+        Value::Map(pairs) => pairs
+            .iter()
+            .map(|(k, v)| child(node, PathSegment::MapKey(k), v))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn select<'a>(selectors: &[Selector], root: &'a Value, node: &Node<'a>, out: &mut Vec<Node<'a>>) {
+    for selector in selectors {
+        match (selector, node.1) {
+            (Selector::Name(name), Value::Object(o)) => {
+                if let Some((k, v)) = o.get_index_of(name).and_then(|i| o.get_index(i)) {
+                    out.push(child(node, PathSegment::Key(k), v));
+                }
+            }
+            (Selector::Name(name), Value::Map(pairs)) => {
+                for (k, v) in pairs {
+                    if matches!(k, Value::String(s) if s == name) {
+                        out.push(child(node, PathSegment::MapKey(k), v));
+                    }
+                }
+            }
+            (Selector::Wildcard, _) => out.extend(children(node)),
+            (Selector::Index(i), Value::Array(arr)) => {
+                let i = if *i < 0 { arr.len() as i64 + i } else { *i };
+                if i >= 0 && (i as usize) < arr.len() {
+                    out.push(child(
+                        node,
+                        PathSegment::Index(i as usize),
+                        &arr[i as usize],
+                    ));
+                }
+            }
+            (Selector::Slice(start, end, step), Value::Array(arr)) => {
+                for i in slice_indices(arr.len() as i64, *start, *end, *step) {
+                    out.push(child(node, PathSegment::Index(i), &arr[i]));
+                }
+            }
+            (Selector::Filter(expr), _) => {
+                for c in children(node) {
+                    if test(expr, root, c.1) {
+                        out.push(c);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn slice_indices(len: i64, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    let normalize = |i: i64| if i < 0 { len + i } else { i };
+    let mut result = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            result.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(i) => i,
+                None => break,
+            };
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = end.map(normalize).unwrap_or(-1).clamp(-1, len - 1);
+        let mut i = upper;
+        while i > lower {
+            result.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(i) => i,
+                None => break,
+            };
+        }
+    }
+    result
+}
+
+fn test(expr: &Expr, root: &Value, current: &Value) -> bool {
+    match expr {
+        Expr::Or(a, b) => test(a, root, current) || test(b, root, current),
+        Expr::And(a, b) => test(a, root, current) && test(b, root, current),
+        Expr::Not(e) => !test(e, root, current),
+        Expr::Exists(path) => !resolve(path, root, current).is_empty(),
+        Expr::Compare(a, op, b) => {
+            let a = operand(a, root, current);
+            let b = operand(b, root, current);
+            compare(a, *op, b)
+        }
+    }
+}
+
+fn resolve<'a>(path: &PathExpr, root: &'a Value, current: &'a Value) -> Vec<Node<'a>> {
+    let start = if path.absolute { root } else { current };
+    evaluate(&path.segments, root, (Vec::new(), start))
+}
+
+// The value of an operand, or None when a path does not select exactly one
+// node.
+fn operand<'a>(op: &'a Operand, root: &'a Value, current: &'a Value) -> Option<&'a Value> {
+    match op {
+        Operand::Literal(v) => Some(v),
+        Operand::Path(path) => {
+            let nodes = resolve(path, root, current);
+            if nodes.len() == 1 {
+                Some(nodes[0].1)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn compare(a: Option<&Value>, op: CmpOp, b: Option<&Value>) -> bool {
+    let equal = match (a, b) {
+        (None, None) => true,
+        (Some(x), Some(y)) => match compare_numbers(x, y) {
+            Some(o) => o == Ordering::Equal,
+            None => x == y,
+        },
+        _ => false,
+    };
+    let less = |x: Option<&Value>, y: Option<&Value>| match (x, y) {
+        (Some(Value::String(x)), Some(Value::String(y))) => x < y,
+        (Some(x), Some(y)) => compare_numbers(x, y) == Some(Ordering::Less),
+        _ => false,
+    };
+    match op {
+        CmpOp::Eq => equal,
+        CmpOp::Ne => !equal,
+        CmpOp::Lt => less(a, b),
+        CmpOp::Le => less(a, b) || equal,
+        CmpOp::Gt => less(b, a),
+        CmpOp::Ge => less(b, a) || equal,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> QueryError {
+        QueryError::InvalidSyntax(format!("{} at {}", message, self.pos))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        let n = s.chars().count();
+        if self.chars.len() >= self.pos + n
+            && self.chars[self.pos..self.pos + n]
+                .iter()
+                .copied()
+                .eq(s.chars())
+        {
+            self.pos += n;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), QueryError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_segments(&mut self) -> Result<Vec<Segment>, QueryError> {
+        let mut segments = Vec::new();
+        loop {
+            let segment = if self.eat_str("..") {
+                let selector = if self.peek() == Some('[') {
+                    None
+                } else {
+                    Some(self.parse_dot_selector()?)
+                };
+                Segment {
+                    descendant: true,
+                    selectors: match selector {
+                        Some(s) => vec![s],
+                        None => self.parse_bracket()?,
+                    },
+                }
+            } else if self.eat('.') {
+                Segment {
+                    descendant: false,
+                    selectors: vec![self.parse_dot_selector()?],
+                }
+            } else if self.peek() == Some('[') {
+                Segment {
+                    descendant: false,
+                    selectors: self.parse_bracket()?,
+                }
+            } else {
+                return Ok(segments);
+            };
+            segments.push(segment);
+        }
+    }
+
+    fn parse_dot_selector(&mut self) -> Result<Selector, QueryError> {
+        if self.eat('*') {
+            return Ok(Selector::Wildcard);
+        }
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            let allowed = c == '_'
+                || c.is_ascii_alphabetic()
+                || !c.is_ascii()
+                || (self.pos > start && c.is_ascii_digit());
+            if !allowed {
+                break;
+            }
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.error("expected a member name"));
+        }
+        Ok(Selector::Name(self.chars[start..self.pos].iter().collect()))
+    }
+
+    fn parse_bracket(&mut self) -> Result<Vec<Selector>, QueryError> {
+        self.expect('[')?;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.parse_selector()?);
+            self.skip_whitespace();
+            if self.eat(']') {
+                return Ok(selectors);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, QueryError> {
+        match self.peek() {
+            Some('\'') | Some('"') => Ok(Selector::Name(self.parse_string()?)),
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                Ok(Selector::Filter(self.parse_or()?))
+            }
+            _ => {
+                let start = self.parse_opt_int()?;
+                self.skip_whitespace();
+                if !self.eat(':') {
+                    return match start {
+                        Some(i) => Ok(Selector::Index(i)),
+                        None => Err(self.error("expected a selector")),
+                    };
+                }
+                self.skip_whitespace();
+                let end = self.parse_opt_int()?;
+                self.skip_whitespace();
+                let mut step = 1;
+                if self.eat(':') {
+                    self.skip_whitespace();
+                    step = self.parse_opt_int()?.unwrap_or(1);
+                }
+                Ok(Selector::Slice(start, end, step))
+            }
+        }
+    }
+
+    fn parse_opt_int(&mut self) -> Result<Option<i64>, QueryError> {
+        let start = self.pos;
+        self.eat('-');
+        while let Some('0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Ok(None);
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse()
+            .map(Some)
+            .map_err(|_| self.error("invalid integer"))
+    }
+
+    fn parse_string(&mut self) -> Result<String, QueryError> {
+        let quote = self.peek().ok_or_else(|| self.error("expected a string"))?;
+        self.pos += 1;
+        let mut result = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(result);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    let c = match self.peek() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            let hex: String =
+                                self.chars.iter().skip(self.pos + 1).take(4).collect();
+                            let code = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .filter(|_| hex.len() == 4)
+                                .and_then(std::char::from_u32)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?;
+                            self.pos += 4;
+                            code
+                        }
+                        Some(c @ '\\') | Some(c @ '/') | Some(c @ '\'') | Some(c @ '"') => c,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    self.pos += 1;
+                    result.push(c);
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    result.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.parse_and()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat_str("||") {
+                return Ok(expr);
+            }
+            self.skip_whitespace();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat_str("&&") {
+                return Ok(expr);
+            }
+            self.skip_whitespace();
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, QueryError> {
+        if self.eat('!') {
+            self.skip_whitespace();
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat('(') {
+            self.skip_whitespace();
+            let expr = self.parse_or()?;
+            self.skip_whitespace();
+            self.expect(')')?;
+            return Ok(expr);
+        }
+
+        let left = self.parse_operand()?;
+        self.skip_whitespace();
+        let op = if self.eat_str("==") {
+            CmpOp::Eq
+        } else if self.eat_str("!=") {
+            CmpOp::Ne
+        } else if self.eat_str("<=") {
+            CmpOp::Le
+        } else if self.eat_str(">=") {
+            CmpOp::Ge
+        } else if self.eat('<') {
+            CmpOp::Lt
+        } else if self.eat('>') {
+            CmpOp::Gt
+        } else {
+            return match left {
+                Operand::Path(path) => Ok(Expr::Exists(path)),
+                Operand::Literal(_) => Err(self.error("expected a comparison")),
+            };
+        };
+        self.skip_whitespace();
+        let right = self.parse_operand()?;
+        Ok(Expr::Compare(left, op, right))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, QueryError> {
+        let literal = match self.peek() {
+            Some('@') | Some('$') => {
+                let absolute = self.peek() == Some('$');
+                self.pos += 1;
+                let segments = self.parse_segments()?;
+                return Ok(Operand::Path(PathExpr { absolute, segments }));
+            }
+            Some('\'') | Some('"') => Value::String(self.parse_string()?),
+            Some('-') | Some('0'..='9') => self.parse_number()?,
+            _ if self.eat_str("true") => Value::Bool(true),
+            _ if self.eat_str("false") => Value::Bool(false),
+            _ if self.eat_str("null") => Value::Null,
+            _ => return Err(self.error("expected a path or a literal")),
+        };
+        Ok(Operand::Literal(literal))
+    }
+
+    fn parse_number(&mut self) -> Result<Value, QueryError> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_digit() || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E') {
+                break;
+            }
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        if let Ok(n) = text.parse::<i64>() {
+            return Ok(Value::Int(n));
+        }
+        text.parse::<f64>()
+            .map(Value::F64)
+            .map_err(|_| self.error("invalid number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc() -> Value {
+        value!({
+            "events": [
+                {"user": {"id": 1}, "tags": [{"name": "x"}, {"name": "y"}]},
+                {"user": {"id": 2}, "tags": [{"name": "x", "n": 2.0}]},
+                {"kind": "empty"}
+            ],
+            "limit": 2,
+            "tags": [{"name": "z"}]
+        })
+    }
+
+    fn pointers(v: &Value, expr: &str) -> Vec<String> {
+        v.query(expr).unwrap().iter().map(|m| m.pointer()).collect()
+    }
+
+    #[test]
+    fn test_members_and_wildcards() {
+        let v = doc();
+        let ids: Vec<&Value> = v
+            .query("$.events[*].user.id")
+            .unwrap()
+            .into_iter()
+            .map(|m| m.value)
+            .collect();
+        assert_eq!(ids, vec![&Value::Int(1), &Value::Int(2)]);
+        assert_eq!(
+            pointers(&v, "$.events[*].user.id"),
+            vec!["/events/0/user/id", "/events/1/user/id"]
+        );
+        assert_eq!(pointers(&v, "$['limit']"), vec!["/limit"]);
+        assert_eq!(
+            pointers(&v, "$[\"events\"][2].kind"),
+            vec!["/events/2/kind"]
+        );
+        assert_eq!(pointers(&v, "$.*").len(), 3);
+        assert!(pointers(&v, "$.missing.x").is_empty());
+        assert_eq!(pointers(&v, "$"), vec![""]);
+    }
+
+    #[test]
+    fn test_indexes_and_slices() {
+        let v = value!([0, 1, 2, 3, 4, 5]);
+        let values = |expr: &str| -> Vec<Value> {
+            v.query(expr)
+                .unwrap()
+                .into_iter()
+                .map(|m| m.value.clone())
+                .collect()
+        };
+        assert_eq!(values("$[-1]"), vec![value!(5)]);
+        assert_eq!(values("$[0, 2]"), vec![value!(0), value!(2)]);
+        assert_eq!(values("$[1:3]"), vec![value!(1), value!(2)]);
+        assert_eq!(values("$[4:]"), vec![value!(4), value!(5)]);
+        assert_eq!(values("$[::2]"), vec![value!(0), value!(2), value!(4)]);
+        assert_eq!(values("$[::-2]"), vec![value!(5), value!(3), value!(1)]);
+        assert_eq!(values("$[-2:-4:-1]"), vec![value!(4), value!(3)]);
+        assert!(values("$[9]").is_empty());
+        assert!(values("$[::0]").is_empty());
+
+        // Extreme bounds and steps are clamped and never overflow.
+        let max = i64::MAX;
+        let min = i64::MIN;
+        assert_eq!(values(&format!("$[1::{}]", max)), vec![value!(1)]);
+        assert_eq!(values(&format!("$[-1::{}]", min)), vec![value!(5)]);
+        assert_eq!(
+            values(&format!("$[{}:{}:{}]", min, max, max)),
+            vec![value!(0)]
+        );
+        assert_eq!(
+            values(&format!("$[{}:{}:{}]", max, min, min)),
+            vec![value!(5)]
+        );
+        assert_eq!(values(&format!("$[{}:2]", min)), vec![value!(0), value!(1)]);
+        assert_eq!(values(&format!("$[{}::-1]", max)).len(), 6);
+        assert!(values(&format!("$[{}:]", max)).is_empty());
+        assert!(values(&format!("$[{}]", min)).is_empty());
+        assert!(values(&format!("$[{}]", max)).is_empty());
+    }
+
+    #[test]
+    fn test_descendants_and_filters() {
+        let v = doc();
+        assert_eq!(
+            pointers(&v, "$..tags[?(@.name == \"x\")]"),
+            vec!["/events/0/tags/0", "/events/1/tags/0"]
+        );
+        assert_eq!(
+            pointers(&v, "$..id"),
+            vec!["/events/0/user/id", "/events/1/user/id"]
+        );
+        assert_eq!(
+            pointers(&v, "$.events[?@.user.id >= $.limit]"),
+            vec!["/events/1"]
+        );
+        assert_eq!(pointers(&v, "$.events[?!@.user]"), vec!["/events/2"]);
+        assert_eq!(
+            pointers(&v, "$..[?@.n == 2 && (@.name == 'x' || @.name == 'q')]"),
+            vec!["/events/1/tags/0"]
+        );
+        assert_eq!(
+            pointers(&v, "$.events[?@.kind != 'empty'].user.id").len(),
+            2
+        );
+        assert_eq!(pointers(&v, "$..name").len(), 4);
+    }
+
+    #[test]
+    fn test_borrowed_matches() {
+        let v = doc();
+        let query = Query::compile("$.events[0].user").unwrap();
+        let matches = query.find(&v);
+        assert!(std::ptr::eq(matches[0].value, &v["events"][0]["user"]));
+        assert_eq!(
+            matches[0].path,
+            vec![
+                PathSegment::Key("events"),
+                PathSegment::Index(0),
+                PathSegment::Key("user")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        for bad in &[
+            "",
+            "events",
+            "$.",
+            "$[",
+            "$['a'",
+            "$[?@.a ==]",
+            "$[?1]",
+            "$.a b",
+            "$[?(@.a]",
+        ] {
+            assert!(Query::compile(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(
+            Query::compile("$.a b").unwrap_err(),
+            QueryError::InvalidSyntax("unexpected character at 3".to_string())
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Rendering `Value` for logs, with secrets masked and large values cut.
+//!
+//! A `Redactor` holds the rules and `Value::redacted` pairs it with a value
+//! as a `Display` adapter. Output is compact JSON like `{}` on `Value`, and
+//! rendering writes straight to the formatter without allocating.
+//!
+//! Mask patterns are matched against the path of object members, map
+//! entries with string keys and array elements:
+//! - A pattern without dots, such as `password`, masks members with that key
+//!   at any depth.
+//! - A dotted pattern is matched from the root, one segment per level: `*`
+//!   matches any single key or index, `**` matches any number of levels and
+//!   `*` inside a segment matches any run of characters, so `*.token` masks
+//!   `token` one level down and `**.*_key` every key ending in `_key`.
+//!
+//! Limits cut strings to a number of characters, binaries to a number of
+//! bytes and arrays to a number of elements, ending each with
+//! `…(N more)`. Arrays, objects and maps nested deeper than the depth limit
+//! are shown as a marker only.
+
+use crate::format::{write_escaped, write_escaped_chars};
+use crate::time;
+use crate::value::Value;
+use std::fmt::{self, Write};
+
+const MASK: &str = "\"[REDACTED]\"";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    AnyDepth,
+    Glob(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redactor {
+    masks: Vec<Vec<Segment>>,
+    max_string_len: Option<usize>,
+    max_binary_len: Option<usize>,
+    max_array_len: Option<usize>,
+    max_depth: Option<usize>,
+}
+
+/// `Display` adapter returned by `Value::redacted`.
+#[derive(Debug, Clone, Copy)]
+pub struct Redacted<'a> {
+    value: &'a Value,
+    redactor: &'a Redactor,
+}
+
+// The path to the node being written, kept on the stack.
+#[derive(Clone, Copy)]
+enum Step<'a> {
+    Key(&'a str),
+    Index(usize),
+    Other,
+}
+
+struct Path<'a> {
+    parent: Option<&'a Path<'a>>,
+    step: Step<'a>,
+}
+
+impl Redactor {
+    /// No masks and no limits.
+    pub fn new() -> Redactor {
+        Redactor::default()
+    }
+
+    /// Masks the values at paths matching `pattern`.
+    pub fn mask(mut self, pattern: &str) -> Redactor {
+        let mut segments: Vec<Segment> = pattern
+            .split('.')
+            .map(|s| match s {
+                "**" => Segment::AnyDepth,
+                s => Segment::Glob(s.to_string()),
+            })
+            .collect();
+        if !pattern.contains('.') {
+            segments.insert(0, Segment::AnyDepth);
+        }
+        self.masks.push(segments);
+        self
+    }
+
+    /// Shows at most `chars` characters of each string.
+    pub fn max_string_len(mut self, chars: usize) -> Redactor {
+        self.max_string_len = Some(chars);
+        self
+    }
+
+    /// Shows at most `bytes` bytes of each binary and extension payload.
+    pub fn max_binary_len(mut self, bytes: usize) -> Redactor {
+        self.max_binary_len = Some(bytes);
+        self
+    }
+
+    /// Shows at most `items` elements of each array.
+    pub fn max_array_len(mut self, items: usize) -> Redactor {
+        self.max_array_len = Some(items);
+        self
+    }
+
+    /// Shows `depth` levels of nesting: with 1, the members of the root
+    /// are written but containers inside them are not.
+    pub fn max_depth(mut self, depth: usize) -> Redactor {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    fn is_masked(&self, path: Option<&Path<'_>>) -> bool {
+        self.masks.iter().any(|mask| matches_path(mask, path))
+    }
+
+    fn too_deep(&self, depth: usize) -> bool {
+        self.max_depth.is_some_and(|max| depth >= max)
+    }
+
+    fn write_value(
+        &self,
+        v: &Value,
+        path: Option<&Path<'_>>,
+        depth: usize,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        if self.is_masked(path) {
+            return out.write_str(MASK);
+        }
+        match v {
+            Value::Null => out.write_str("null"),
+            Value::Bool(b) => write!(out, "{}", b),
+            Value::Int(n) => write!(out, "{}", n),
+            Value::UInt(n) => write!(out, "{}", n),
+            Value::F32(n) if n.is_finite() => write!(out, "{:?}", n),
+            Value::F64(n) if n.is_finite() => write!(out, "{:?}", n),
+            Value::F32(_) | Value::F64(_) => out.write_str("null"),
+            Value::String(s) => {
+                out.write_char('"')?;
+                self.write_string(s, out)?;
+                out.write_char('"')
+            }
+            Value::Binary(b) => {
+                out.write_char('"')?;
+                self.write_binary(b, out)?;
+                out.write_char('"')
+            }
+            Value::Ext { type_id, data } => {
+                write!(out, "{{\"ext\":{},\"data\":\"", type_id)?;
+                self.write_binary(data, out)?;
+                out.write_str("\"}")
+            }
+            Value::Timestamp { secs, nanos } => {
+                out.write_char('"')?;
+                time::write_rfc3339(*secs, *nanos, out)?;
+                out.write_char('"')
+            }
+            Value::Array(arr) => {
+                let shown = if arr.is_empty() || !self.too_deep(depth) {
+                    arr.len().min(self.max_array_len.unwrap_or(usize::MAX))
+                } else {
+                    0
+                };
+                out.write_char('[')?;
+                for (i, item) in arr[..shown].iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    let child = Path {
+                        parent: path,
+                        step: Step::Index(i),
+                    };
+                    self.write_value(item, Some(&child), depth + 1, out)?;
+                }
+                if shown < arr.len() {
+                    if shown > 0 {
+                        out.write_char(',')?;
+                    }
+                    write!(out, "\"…({} more)\"", arr.len() - shown)?;
+                }
+                out.write_char(']')
+            }
+            Value::Object(o) => {
+                if !o.is_empty() && self.too_deep(depth) {
+                    return write!(out, "{{\"…\":\"({} more)\"}}", o.len());
+                }
+                out.write_char('{')?;
+                for (i, (k, item)) in o.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    write_escaped(k, out)?;
+                    out.write_char(':')?;
+                    let child = Path {
+                        parent: path,
+                        step: Step::Key(k),
+                    };
+                    self.write_value(item, Some(&child), depth + 1, out)?;
+                }
+                out.write_char('}')
+            }
+            Value::Map(pairs) => {
+                if !pairs.is_empty() && self.too_deep(depth) {
+                    return write!(out, "{{\"…\":\"({} more)\"}}", pairs.len());
+                }
+                out.write_char('{')?;
+                for (i, (k, item)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    self.write_key(k, depth, out)?;
+                    out.write_char(':')?;
+                    let child = Path {
+                        parent: path,
+                        step: match k {
+                            Value::String(s) => Step::Key(s),
+                            _ => Step::Other,
+                        },
+                    };
+                    self.write_value(item, Some(&child), depth + 1, out)?;
+                }
+                out.write_char('}')
+            }
+        }
+    }
+
+    // Map keys become strings, as in `Formatter`.
+    fn write_key(&self, key: &Value, depth: usize, out: &mut dyn Write) -> fmt::Result {
+        match key {
+            Value::String(s) => write_escaped(s, out),
+            Value::Binary(_) | Value::Timestamp { .. } => self.write_value(key, None, depth, out),
+            _ => {
+                out.write_char('"')?;
+                self.write_value(key, None, depth + 1, &mut Escaped(out))?;
+                out.write_char('"')
+            }
+        }
+    }
+
+    fn write_string(&self, s: &str, out: &mut dyn Write) -> fmt::Result {
+        let max = match self.max_string_len {
+            Some(max) => max,
+            None => return write_escaped_chars(s, out),
+        };
+        match s.char_indices().nth(max) {
+            Some((end, _)) => {
+                write_escaped_chars(&s[..end], out)?;
+                write!(out, "…({} more)", s[end..].chars().count())
+            }
+            None => write_escaped_chars(s, out),
+        }
+    }
+
+    fn write_binary(&self, data: &[u8], out: &mut dyn Write) -> fmt::Result {
+        let shown = data.len().min(self.max_binary_len.unwrap_or(usize::MAX));
+        for b in &data[..shown] {
+            write!(out, "{:02x}", b)?;
+        }
+        if shown < data.len() {
+            write!(out, "…({} more)", data.len() - shown)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Redacted<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.redactor.write_value(self.value, None, 0, f)
+    }
+}
+
+impl Value {
+    /// Renders this value through `redactor`, for example
+    /// `log::info!("{}", payload.redacted(&REDACTOR))`.
+    pub fn redacted<'a>(&'a self, redactor: &'a Redactor) -> Redacted<'a> {
+        Redacted {
+            value: self,
+            redactor,
+        }
+    }
+}
+
+// Escapes everything written through it, to nest a rendering in a string.
+struct Escaped<'a>(&'a mut dyn Write);
+
+impl<'a> Write for Escaped<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_escaped_chars(s, self.0)
+    }
+}
+
+// Matches from the leaf up, since the path only links to parents.
+fn matches_path(pattern: &[Segment], path: Option<&Path<'_>>) -> bool {
+    match pattern.split_last() {
+        None => path.is_none(),
+        Some((Segment::AnyDepth, rest)) => {
+            matches_path(rest, path) || path.is_some_and(|p| matches_path(pattern, p.parent))
+        }
+        Some((Segment::Glob(glob), rest)) => match path {
+            Some(p) => step_matches(glob, p.step) && matches_path(rest, p.parent),
+            None => false,
+        },
+    }
+}
+
+fn step_matches(glob: &str, step: Step<'_>) -> bool {
+    match step {
+        Step::Key(k) => glob_match(glob.as_bytes(), k.as_bytes()),
+        Step::Index(mut i) => {
+            let mut digits = [0u8; 20];
+            let mut start = digits.len();
+            loop {
+                start -= 1;
+                digits[start] = b'0' + (i % 10) as u8;
+                i /= 10;
+                if i == 0 {
+                    break;
+                }
+            }
+            glob_match(glob.as_bytes(), &digits[start..])
+        }
+        Step::Other => glob.bytes().all(|b| b == b'*'),
+    }
+}
+
+// `*` matches any run of bytes, everything else matches itself.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&b| b == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masks() {
+        let v = value!({
+            "user": {"name": "a", "password": "p", "token": "t"},
+            "token": "root",
+            "keys": [{"api_key": "k", "id": 1}],
+            "password": {"nested": true}
+        });
+        let r = Redactor::new().mask("password").mask("*.token");
+        assert_eq!(
+            v.redacted(&r).to_string(),
+            "{\"user\":{\"name\":\"a\",\"password\":\"[REDACTED]\",\"token\":\"[REDACTED]\"},\
+             \"token\":\"root\",\"keys\":[{\"api_key\":\"k\",\"id\":1}],\
+             \"password\":\"[REDACTED]\"}"
+        );
+
+        let r = Redactor::new().mask("**.*_key").mask("keys.*.id");
+        assert_eq!(
+            v["keys"].redacted(&Redactor::new()).to_string(),
+            "[{\"api_key\":\"k\",\"id\":1}]"
+        );
+        assert!(v
+            .redacted(&r)
+            .to_string()
+            .contains("[{\"api_key\":\"[REDACTED]\",\"id\":\"[REDACTED]\"}]"));
+
+        let r = Redactor::new().mask("keys.0");
+        assert!(v
+            .redacted(&r)
+            .to_string()
+            .contains("\"keys\":[\"[REDACTED]\"]"));
+
+        let m = Value::Map(vec![(value!("secret"), value!(1)), (value!(2), value!(3))]);
+        let r = Redactor::new().mask("secret");
+        assert_eq!(
+            m.redacted(&r).to_string(),
+            "{\"secret\":\"[REDACTED]\",\"2\":3}"
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let v = value!({"s": "héllo world", "a": [1, 2, 3, 4], "deep": {"x": [[1]]}});
+        let r = Redactor::new().max_string_len(5).max_array_len(2);
+        assert_eq!(
+            v.redacted(&r).to_string(),
+            "{\"s\":\"héllo…(6 more)\",\"a\":[1,2,\"…(2 more)\"],\"deep\":{\"x\":[[1]]}}"
+        );
+
+        let r = Redactor::new().max_depth(2);
+        assert_eq!(
+            v.redacted(&r).to_string(),
+            "{\"s\":\"héllo world\",\"a\":[1,2,3,4],\"deep\":{\"x\":[\"…(1 more)\"]}}"
+        );
+        let r = Redactor::new().max_depth(0);
+        assert_eq!(v.redacted(&r).to_string(), "{\"…\":\"(3 more)\"}");
+        assert_eq!(value!([]).redacted(&r).to_string(), "[]");
+
+        let r = Redactor::new().max_binary_len(2).max_array_len(0);
+        assert_eq!(
+            Value::Binary(vec![1, 2, 3, 4]).redacted(&r).to_string(),
+            "\"0102…(2 more)\""
+        );
+        assert_eq!(value!([1]).redacted(&r).to_string(), "[\"…(1 more)\"]");
+    }
+
+    #[test]
+    fn test_matches_display_without_rules() {
+        let v = value!({"a": [1, 2.5, null, "x\n"], "b": {"c": true}});
+        assert_eq!(v.redacted(&Redactor::new()).to_string(), v.to_string());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"a*c", b"abbc"));
+        assert!(glob_match(b"*_key", b"api_key"));
+        assert!(!glob_match(b"*_key", b"api_keys"));
+        assert!(glob_match(b"**", b"x"));
+        assert!(!glob_match(b"ab", b"a"));
+    }
+}
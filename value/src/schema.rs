@@ -0,0 +1,597 @@
+//! Validating `Value` documents against a schema.
+//!
+//! Schemas are a subset of JSON Schema, written as a `Value` themselves:
+//!
+//! - `type`: a type name or an array of them. The JSON names `null`,
+//!   `boolean`, `integer`, `number`, `string`, `array` and `object` are
+//!   supported, plus `binary`, `ext`, `timestamp` and `map` for the variants
+//!   JSON lacks. `integer` also accepts floats without a fraction.
+//! - `enum`, `const`
+//! - `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum`
+//! - `minLength`, `maxLength`, `pattern` for strings; lengths count
+//!   characters
+//! - `items`, `minItems`, `maxItems` for arrays
+//! - `properties`, `required`, `additionalProperties` for objects
+//!
+//! `true` and `false` are schemas that accept and reject everything. Other
+//! keywords are ignored, as JSON Schema does with unknown keywords.
+
+use crate::cmp::compare_numbers;
+use crate::pattern::Pattern;
+use crate::value::Value;
+use crate::visit::{path_to_pointer, PathSegment};
+use std::cmp::Ordering;
+use std::error::Error as StdError;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    InvalidKeyword(String),
+    InvalidPattern(String),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::InvalidKeyword(p) => write!(f, "InvalidKeyword: {}", p),
+            SchemaError::InvalidPattern(p) => write!(f, "InvalidPattern: {}", p),
+        }
+    }
+}
+
+impl StdError for SchemaError {}
+
+/// One violation: where it is, which keyword failed and a readable message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// JSON Pointer to the offending value, `""` for the root.
+    pub path: String,
+    pub keyword: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at \"{}\": {}", self.keyword, self.path, self.message)
+    }
+}
+
+impl StdError for ValidationError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array,
+    Object,
+    Binary,
+    Ext,
+    Timestamp,
+    Map,
+}
+
+impl Type {
+    fn parse(name: &str) -> Option<Type> {
+        let t = match name {
+            "null" => Type::Null,
+            "boolean" => Type::Boolean,
+            "integer" => Type::Integer,
+            "number" => Type::Number,
+            "string" => Type::String,
+            "array" => Type::Array,
+            "object" => Type::Object,
+            "binary" => Type::Binary,
+            "ext" => Type::Ext,
+            "timestamp" => Type::Timestamp,
+            "map" => Type::Map,
+            _ => return None,
+        };
+        Some(t)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Null => "null",
+            Type::Boolean => "boolean",
+            Type::Integer => "integer",
+            Type::Number => "number",
+            Type::String => "string",
+            Type::Array => "array",
+            Type::Object => "object",
+            Type::Binary => "binary",
+            Type::Ext => "ext",
+            Type::Timestamp => "timestamp",
+            Type::Map => "map",
+        }
+    }
+
+    fn of(v: &Value) -> Type {
+        match v {
+            Value::Null => Type::Null,
+            Value::Bool(_) => Type::Boolean,
+            Value::Int(_) | Value::UInt(_) => Type::Integer,
+            Value::F32(_) | Value::F64(_) => Type::Number,
+            Value::String(_) => Type::String,
+            Value::Binary(_) => Type::Binary,
+            Value::Ext { .. } => Type::Ext,
+            Value::Timestamp { .. } => Type::Timestamp,
+            Value::Array(_) => Type::Array,
+            Value::Object(_) => Type::Object,
+            Value::Map(_) => Type::Map,
+        }
+    }
+
+    fn accepts(self, v: &Value) -> bool {
+        match (self, v) {
+            (Type::Number, _) => Type::of(v) == Type::Integer || Type::of(v) == Type::Number,
+            (Type::Integer, Value::F32(f)) => f.fract() == 0.0,
+            (Type::Integer, Value::F64(f)) => f.fract() == 0.0,
+            (t, v) => t == Type::of(v),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Bool(bool),
+    Rules(Box<Rules>),
+}
+
+#[derive(Debug, Clone, Default)]
+struct Rules {
+    types: Option<Vec<Type>>,
+    enumeration: Option<Vec<Value>>,
+    constant: Option<Value>,
+    minimum: Option<Value>,
+    maximum: Option<Value>,
+    exclusive_minimum: Option<Value>,
+    exclusive_maximum: Option<Value>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    pattern: Option<(String, Pattern)>,
+    items: Option<Node>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    properties: Vec<(String, Node)>,
+    required: Vec<String>,
+    additional_properties: Option<Node>,
+}
+
+/// A compiled schema. Compile once, validate any number of documents.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    root: Node,
+}
+
+impl Schema {
+    pub fn compile(schema: &Value) -> Result<Schema, SchemaError> {
+        let mut path = Vec::new();
+        Ok(Schema {
+            root: compile_node(schema, &mut path)?,
+        })
+    }
+
+    /// Every violation in `v`, in document order. Empty when `v` is valid.
+    pub fn validate(&self, v: &Value) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let mut path = Vec::new();
+        validate_node(&self.root, v, &mut path, &mut errors);
+        errors
+    }
+
+    pub fn is_valid(&self, v: &Value) -> bool {
+        self.validate(v).is_empty()
+    }
+}
+
+fn invalid(path: &[PathSegment<'_>], keyword: &str) -> SchemaError {
+    let mut path = path.to_vec();
+    path.push(PathSegment::Key(keyword));
+    SchemaError::InvalidKeyword(path_to_pointer(&path))
+}
+
+fn compile_node<'a>(
+    schema: &'a Value,
+    path: &mut Vec<PathSegment<'a>>,
+) -> Result<Node, SchemaError> {
+    let o = match schema {
+        Value::Bool(b) => return Ok(Node::Bool(*b)),
+        Value::Object(o) => o,
+        _ => return Err(SchemaError::InvalidKeyword(path_to_pointer(path))),
+    };
+
+    let mut rules = Rules::default();
+    for (keyword, v) in o {
+        let keyword = keyword.as_str();
+        let bad = || invalid(path, keyword);
+        match keyword {
+            "type" => {
+                let names = match v {
+                    Value::String(_) => std::slice::from_ref(v),
+                    Value::Array(names) => names.as_slice(),
+                    _ => return Err(bad()),
+                };
+                let mut types = Vec::new();
+                for name in names {
+                    match name {
+                        Value::String(name) => types.push(Type::parse(name).ok_or_else(bad)?),
+                        _ => return Err(bad()),
+                    }
+                }
+                rules.types = Some(types);
+            }
+            "enum" => match v {
+                Value::Array(values) => rules.enumeration = Some(values.clone()),
+                _ => return Err(bad()),
+            },
+            "const" => rules.constant = Some(v.clone()),
+            "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" => {
+                if !Type::Number.accepts(v) {
+                    return Err(bad());
+                }
+                let bound = Some(v.clone());
+                match keyword {
+                    "minimum" => rules.minimum = bound,
+                    "maximum" => rules.maximum = bound,
+                    "exclusiveMinimum" => rules.exclusive_minimum = bound,
+                    _ => rules.exclusive_maximum = bound,
+                }
+            }
+            "minLength" => rules.min_length = Some(count(v).ok_or_else(bad)?),
+            "maxLength" => rules.max_length = Some(count(v).ok_or_else(bad)?),
+            "minItems" => rules.min_items = Some(count(v).ok_or_else(bad)?),
+            "maxItems" => rules.max_items = Some(count(v).ok_or_else(bad)?),
+            "pattern" => match v {
+                Value::String(source) => {
+                    let pattern = Pattern::new(source)
+                        .map_err(|e| SchemaError::InvalidPattern(format!("{}: {}", bad(), e)))?;
+                    rules.pattern = Some((source.clone(), pattern));
+                }
+                _ => return Err(bad()),
+            },
+            "items" => {
+                path.push(PathSegment::Key("items"));
+                rules.items = Some(compile_node(v, path)?);
+                path.pop();
+            }
+            "properties" => {
+                let properties = match v {
+                    Value::Object(properties) => properties,
+                    _ => return Err(bad()),
+                };
+                path.push(PathSegment::Key("properties"));
+                for (name, sub) in properties {
+                    path.push(PathSegment::Key(name));
+                    rules
+                        .properties
+                        .push((name.clone(), compile_node(sub, path)?));
+                    path.pop();
+                }
+                path.pop();
+            }
+            "required" => {
+                let names = match v {
+                    Value::Array(names) => names,
+                    _ => return Err(bad()),
+                };
+                for name in names {
+                    match name {
+                        Value::String(name) => rules.required.push(name.clone()),
+                        _ => return Err(bad()),
+                    }
+                }
+            }
+            "additionalProperties" => {
+                path.push(PathSegment::Key("additionalProperties"));
+                rules.additional_properties = Some(compile_node(v, path)?);
+                path.pop();
+            }
+            _ => {}
+        }
+    }
+    Ok(Node::Rules(Box::new(rules)))
+}
+
+fn count(v: &Value) -> Option<usize> {
+    match v {
+        Value::Int(n) if *n >= 0 => Some(*n as usize),
+        Value::UInt(n) => Some(*n as usize),
+        _ => None,
+    }
+}
+
+fn validate_node<'a>(
+    node: &Node,
+    v: &'a Value,
+    path: &mut Vec<PathSegment<'a>>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let rules = match node {
+        Node::Bool(true) => return,
+        Node::Bool(false) => {
+            errors.push(ValidationError {
+                path: path_to_pointer(path),
+                keyword: "false",
+                message: "no value is allowed here".to_string(),
+            });
+            return;
+        }
+        Node::Rules(rules) => rules,
+    };
+    let mut report = |keyword: &'static str, message: String| {
+        errors.push(ValidationError {
+            path: path_to_pointer(path),
+            keyword,
+            message,
+        })
+    };
+
+    if let Some(types) = &rules.types {
+        if !types.iter().any(|t| t.accepts(v)) {
+            let expected: Vec<&str> = types.iter().map(|t| t.name()).collect();
+            report(
+                "type",
+                format!(
+                    "expected {}, found {}",
+                    expected.join(" or "),
+                    Type::of(v).name()
+                ),
+            );
+            // The remaining keywords would only repeat the mismatch.
+            return;
+        }
+    }
+    if let Some(values) = &rules.enumeration {
+        if !values.contains(v) {
+            report("enum", format!("{} is not one of the allowed values", v));
+        }
+    }
+    if let Some(constant) = &rules.constant {
+        if constant != v {
+            report("const", format!("expected {}, found {}", constant, v));
+        }
+    }
+
+    let bounds = [
+        (
+            "minimum",
+            &rules.minimum,
+            [Ordering::Greater, Ordering::Equal],
+        ),
+        ("maximum", &rules.maximum, [Ordering::Less, Ordering::Equal]),
+        (
+            "exclusiveMinimum",
+            &rules.exclusive_minimum,
+            [Ordering::Greater, Ordering::Greater],
+        ),
+        (
+            "exclusiveMaximum",
+            &rules.exclusive_maximum,
+            [Ordering::Less, Ordering::Less],
+        ),
+    ];
+    for (keyword, bound, allowed) in bounds.iter() {
+        if let Some(bound) = bound {
+            if let Some(o) = compare_numbers(v, bound) {
+                if !allowed.contains(&o) {
+                    report(keyword, format!("{} is out of range, limit {}", v, bound));
+                }
+            }
+        }
+    }
+
+    if let Value::String(s) = v {
+        let len = s.chars().count();
+        if let Some(min) = rules.min_length {
+            if len < min {
+                report("minLength", format!("length {} is below {}", len, min));
+            }
+        }
+        if let Some(max) = rules.max_length {
+            if len > max {
+                report("maxLength", format!("length {} is above {}", len, max));
+            }
+        }
+        if let Some((source, pattern)) = &rules.pattern {
+            if !pattern.is_match(s) {
+                report("pattern", format!("does not match {:?}", source));
+            }
+        }
+    }
+
+    match v {
+        Value::Array(arr) => {
+            if let Some(min) = rules.min_items {
+                if arr.len() < min {
+                    report("minItems", format!("{} items, at least {}", arr.len(), min));
+                }
+            }
+            if let Some(max) = rules.max_items {
+                if arr.len() > max {
+                    report("maxItems", format!("{} items, at most {}", arr.len(), max));
+                }
+            }
+            if let Some(items) = &rules.items {
+                for (i, item) in arr.iter().enumerate() {
+                    path.push(PathSegment::Index(i));
+                    validate_node(items, item, path, errors);
+                    path.pop();
+                }
+            }
+        }
+        Value::Object(o) => {
+            for name in &rules.required {
+                if !o.contains_key(name) {
+                    report("required", format!("missing property {:?}", name));
+                }
+            }
+            for (name, child) in o {
+                let declared = rules.properties.iter().find(|(p, _)| p == name);
+                let sub = match declared {
+                    Some((_, sub)) => sub,
+                    None => match &rules.additional_properties {
+                        Some(sub) => sub,
+                        None => continue,
+                    },
+                };
+                path.push(PathSegment::Key(name));
+                if declared.is_none() {
+                    if let Node::Bool(false) = sub {
+                        errors.push(ValidationError {
+                            path: path_to_pointer(path),
+                            keyword: "additionalProperties",
+                            message: format!("property {:?} is not allowed", name),
+                        });
+                        path.pop();
+                        continue;
+                    }
+                }
+                validate_node(sub, child, path, errors);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_schema() -> Schema {
+        Schema::compile(&value!({
+            "type": "object",
+            "required": ["name", "age"],
+            "properties": {
+                "name": {"type": "string", "minLength": 1, "pattern": "^[A-Za-z ]+$"},
+                "age": {"type": "integer", "minimum": 0, "exclusiveMaximum": 150},
+                "role": {"enum": ["admin", "user"]},
+                "tags": {
+                    "type": "array",
+                    "maxItems": 2,
+                    "items": {"type": ["string", "null"]}
+                }
+            },
+            "additionalProperties": false
+        }))
+        .unwrap()
+    }
+
+    fn summary(errors: &[ValidationError]) -> Vec<(String, &'static str)> {
+        errors.iter().map(|e| (e.path.clone(), e.keyword)).collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        let schema = user_schema();
+        assert!(schema.is_valid(&value!({"name": "Ann", "age": 30})));
+        assert!(schema.is_valid(&value!({
+            "name": "Bob Smith",
+            "age": 149.0,
+            "role": "admin",
+            "tags": ["a", null]
+        })));
+    }
+
+    #[test]
+    fn test_reports_every_violation() {
+        let errors = user_schema().validate(&value!({
+            "name": "x1",
+            "age": 150,
+            "role": "root",
+            "tags": ["a", 2, "c"],
+            "extra": true
+        }));
+        assert_eq!(
+            summary(&errors),
+            vec![
+                ("/name".to_string(), "pattern"),
+                ("/age".to_string(), "exclusiveMaximum"),
+                ("/role".to_string(), "enum"),
+                ("/tags".to_string(), "maxItems"),
+                ("/tags/1".to_string(), "type"),
+                ("/extra".to_string(), "additionalProperties"),
+            ]
+        );
+        assert_eq!(
+            errors[4].to_string(),
+            "type at \"/tags/1\": expected string or null, found integer"
+        );
+
+        let errors = user_schema().validate(&value!({"age": -1}));
+        assert_eq!(
+            summary(&errors),
+            vec![
+                ("".to_string(), "required"),
+                ("/age".to_string(), "minimum")
+            ]
+        );
+        assert_eq!(errors[0].message, "missing property \"name\"");
+
+        let errors = user_schema().validate(&value!([1]));
+        assert_eq!(summary(&errors), vec![("".to_string(), "type")]);
+    }
+
+    #[test]
+    fn test_types() {
+        let schema = |t: &str| Schema::compile(&value!({ "type": t })).unwrap();
+        assert!(schema("number").is_valid(&value!(1)));
+        assert!(schema("number").is_valid(&value!(1.5)));
+        assert!(!schema("integer").is_valid(&value!(1.5)));
+        assert!(schema("binary").is_valid(&Value::Binary(vec![])));
+        assert!(schema("timestamp").is_valid(&Value::Timestamp { secs: 0, nanos: 0 }));
+        assert!(!schema("string").is_valid(&Value::Timestamp { secs: 0, nanos: 0 }));
+        assert!(schema("map").is_valid(&Value::Map(vec![])));
+
+        assert!(Schema::compile(&value!(true)).unwrap().is_valid(&value!(1)));
+        assert!(!Schema::compile(&value!(false))
+            .unwrap()
+            .is_valid(&value!(1)));
+        let schema = Schema::compile(&value!({"const": {"a": 1}})).unwrap();
+        assert!(schema.is_valid(&value!({"a": 1})));
+        assert!(!schema.is_valid(&value!({"a": 2})));
+    }
+
+    #[test]
+    fn test_pattern_on_long_strings() {
+        let schema = Schema::compile(&value!({"pattern": "^(a*)*[a-z]+$"})).unwrap();
+        let long = "a".repeat(100_000);
+        assert!(schema.is_valid(&Value::String(long.clone())));
+        let errors = schema.validate(&Value::String(long + "!"));
+        assert_eq!(summary(&errors), vec![("".to_string(), "pattern")]);
+    }
+
+    #[test]
+    fn test_additional_properties_schema() {
+        let schema = Schema::compile(&value!({
+            "properties": {"id": {"type": "integer"}},
+            "additionalProperties": {"type": "string", "maxLength": 3}
+        }))
+        .unwrap();
+        let errors = schema.validate(&value!({"id": 1, "a": "abcd", "b": 2}));
+        assert_eq!(
+            summary(&errors),
+            vec![("/a".to_string(), "maxLength"), ("/b".to_string(), "type")]
+        );
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        assert_eq!(
+            Schema::compile(&value!({"type": "float"})).unwrap_err(),
+            SchemaError::InvalidKeyword("/type".to_string())
+        );
+        assert_eq!(
+            Schema::compile(&value!({"properties": {"a": {"minLength": -1}}})).unwrap_err(),
+            SchemaError::InvalidKeyword("/properties/a/minLength".to_string())
+        );
+        assert!(matches!(
+            Schema::compile(&value!({"pattern": "("})),
+            Err(SchemaError::InvalidPattern(_))
+        ));
+        assert!(Schema::compile(&value!(1)).is_err());
+    }
+}
@@ -0,0 +1,520 @@
+//! An immutable, reference-counted counterpart of `Value`.
+//!
+//! Strings, binaries, arrays, objects and maps sit behind an `Arc`, so
+//! cloning a `SharedValue` only bumps a counter however large the tree is,
+//! and the tree can be handed to other threads. Convert to `Value` with
+//! `to_value()` to modify it.
+//!
+//! Equality and hashing follow `Value`: `Int(1) == UInt(1)`, every NaN
+//! equals every other NaN, and objects and maps are unordered.
+
+use crate::cmp::{cmp_num, hash_num, Num};
+use crate::object_map::ObjectMap;
+use crate::pointer::{parse_array_index, parse_pointer};
+use crate::value::{Value, ValueKind};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub enum SharedValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    F32(f32),
+    F64(f64),
+    String(Arc<str>),
+    Binary(Arc<[u8]>),
+    Ext { type_id: i8, data: Arc<[u8]> },
+    Timestamp { secs: i64, nanos: u32 },
+    Array(Arc<[SharedValue]>),
+    Object(Arc<SharedObject>),
+    Map(Arc<[(SharedValue, SharedValue)]>),
+}
+
+/// The members of a `SharedValue::Object`, in insertion order.
+#[derive(Debug, Default)]
+pub struct SharedObject {
+    entries: Vec<(Arc<str>, SharedValue)>,
+    index: HashMap<Arc<str>, usize>,
+}
+
+impl SharedObject {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&SharedValue> {
+        self.index.get(key).map(|i| &self.entries[*i].1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Arc<str>, &SharedValue)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &SharedValue> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+impl PartialEq for SharedObject {
+    fn eq(&self, other: &SharedObject) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl Eq for SharedObject {}
+
+impl Hash for SharedObject {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut entries: Vec<&(Arc<str>, SharedValue)> = self.entries.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.hash(state);
+    }
+}
+
+impl<'a> From<&'a ObjectMap> for SharedObject {
+    fn from(o: &'a ObjectMap) -> SharedObject {
+        let mut entries = Vec::with_capacity(o.len());
+        let mut index = HashMap::with_capacity(o.len());
+        for (i, (k, v)) in o.iter().enumerate() {
+            let key: Arc<str> = Arc::from(k.as_str());
+            index.insert(key.clone(), i);
+            entries.push((key, SharedValue::from(v)));
+        }
+        SharedObject { entries, index }
+    }
+}
+
+impl SharedValue {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            SharedValue::Null => ValueKind::Null,
+            SharedValue::Bool(_) => ValueKind::Bool,
+            SharedValue::Int(_) => ValueKind::Int,
+            SharedValue::UInt(_) => ValueKind::UInt,
+            SharedValue::F32(_) => ValueKind::F32,
+            SharedValue::F64(_) => ValueKind::F64,
+            SharedValue::String(_) => ValueKind::String,
+            SharedValue::Binary(_) => ValueKind::Binary,
+            SharedValue::Ext { .. } => ValueKind::Ext,
+            SharedValue::Timestamp { .. } => ValueKind::Timestamp,
+            SharedValue::Array(_) => ValueKind::Array,
+            SharedValue::Object(_) => ValueKind::Object,
+            SharedValue::Map(_) => ValueKind::Map,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, SharedValue::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            SharedValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// The integer, if this is an `Int` or a `UInt` that fits in `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            SharedValue::Int(n) => Some(*n),
+            SharedValue::UInt(n) if *n <= i64::MAX as u64 => Some(*n as i64),
+            _ => None,
+        }
+    }
+
+    /// The integer, if this is a `UInt` or a non-negative `Int`.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            SharedValue::UInt(n) => Some(*n),
+            SharedValue::Int(n) if *n >= 0 => Some(*n as u64),
+            _ => None,
+        }
+    }
+
+    /// Any number as `f64`. Integers beyond 2^53 lose precision.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            SharedValue::Int(n) => Some(*n as f64),
+            SharedValue::UInt(n) => Some(*n as f64),
+            SharedValue::F32(f) => Some(*f as f64),
+            SharedValue::F64(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            SharedValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            SharedValue::Binary(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[SharedValue]> {
+        match self {
+            SharedValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&SharedObject> {
+        match self {
+            SharedValue::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// Member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&SharedValue> {
+        self.as_object()?.get(key)
+    }
+
+    /// Looks up a JSON Pointer (RFC 6901), like `Value::pointer`.
+    pub fn pointer(&self, pointer: &str) -> Option<&SharedValue> {
+        let mut target = self;
+        for token in parse_pointer(pointer)? {
+            target = match target {
+                SharedValue::Object(o) => o.get(&token)?,
+                SharedValue::Array(arr) => arr.get(parse_array_index(&token)?)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            SharedValue::Null => Value::Null,
+            SharedValue::Bool(b) => Value::Bool(*b),
+            SharedValue::Int(n) => Value::Int(*n),
+            SharedValue::UInt(n) => Value::UInt(*n),
+            SharedValue::F32(n) => Value::F32(*n),
+            SharedValue::F64(n) => Value::F64(*n),
+            SharedValue::String(s) => Value::String(s.to_string()),
+            SharedValue::Binary(b) => Value::Binary(b.to_vec()),
+            SharedValue::Ext { type_id, data } => Value::Ext {
+                type_id: *type_id,
+                data: data.to_vec(),
+            },
+            SharedValue::Timestamp { secs, nanos } => Value::Timestamp {
+                secs: *secs,
+                nanos: *nanos,
+            },
+            SharedValue::Array(arr) => Value::Array(arr.iter().map(|v| v.to_value()).collect()),
+            SharedValue::Object(o) => {
+                let mut result = ObjectMap::with_capacity(o.len());
+                for (k, v) in o.iter() {
+                    result.insert(k.to_string(), v.to_value());
+                }
+                Value::Object(result)
+            }
+            SharedValue::Map(pairs) => Value::Map(
+                pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_value(), v.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn as_num(v: &SharedValue) -> Option<Num> {
+    match v {
+        SharedValue::Int(n) => Some(Num::Int(*n as i128)),
+        SharedValue::UInt(n) => Some(Num::Int(*n as i128)),
+        SharedValue::F32(f) => Some(Num::Float(*f as f64)),
+        SharedValue::F64(f) => Some(Num::Float(*f)),
+        _ => None,
+    }
+}
+
+// Whether `a` and `b` hold the same pairs in any order. Each pair in `a`
+// takes the first unused equal pair in `b`.
+fn same_pairs(a: &[(SharedValue, SharedValue)], b: &[(SharedValue, SharedValue)]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut used = vec![false; b.len()];
+    a.iter().all(|pair| {
+        let found = (0..b.len()).find(|&i| !used[i] && b[i] == *pair);
+        found.map(|i| used[i] = true).is_some()
+    })
+}
+
+impl PartialEq for SharedValue {
+    fn eq(&self, other: &SharedValue) -> bool {
+        match (self, other) {
+            (SharedValue::Null, SharedValue::Null) => true,
+            (SharedValue::Bool(a), SharedValue::Bool(b)) => a == b,
+            (SharedValue::String(a), SharedValue::String(b)) => a == b,
+            (SharedValue::Binary(a), SharedValue::Binary(b)) => a == b,
+            (
+                SharedValue::Ext {
+                    type_id: ta,
+                    data: da,
+                },
+                SharedValue::Ext {
+                    type_id: tb,
+                    data: db,
+                },
+            ) => ta == tb && da == db,
+            (
+                SharedValue::Timestamp {
+                    secs: sa,
+                    nanos: na,
+                },
+                SharedValue::Timestamp {
+                    secs: sb,
+                    nanos: nb,
+                },
+            ) => sa == sb && na == nb,
+            (SharedValue::Array(a), SharedValue::Array(b)) => a == b,
+            (SharedValue::Object(a), SharedValue::Object(b)) => a == b,
+            (SharedValue::Map(a), SharedValue::Map(b)) => same_pairs(a, b),
+            (a, b) => match (as_num(a), as_num(b)) {
+                (Some(x), Some(y)) => cmp_num(&x, &y) == Ordering::Equal,
+                _ => false,
+            },
+        }
+    }
+}
+
+impl Eq for SharedValue {}
+
+impl Hash for SharedValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            SharedValue::Null => 0u8.hash(state),
+            SharedValue::Bool(b) => {
+                1u8.hash(state);
+                b.hash(state);
+            }
+            SharedValue::Int(_)
+            | SharedValue::UInt(_)
+            | SharedValue::F32(_)
+            | SharedValue::F64(_) => {
+                2u8.hash(state);
+                if let Some(n) = as_num(self) {
+                    hash_num(&n, state);
+                }
+            }
+            SharedValue::String(s) => {
+                3u8.hash(state);
+                s.hash(state);
+            }
+            SharedValue::Binary(b) => {
+                4u8.hash(state);
+                b.hash(state);
+            }
+            SharedValue::Ext { type_id, data } => {
+                5u8.hash(state);
+                type_id.hash(state);
+                data.hash(state);
+            }
+            SharedValue::Timestamp { secs, nanos } => {
+                6u8.hash(state);
+                secs.hash(state);
+                nanos.hash(state);
+            }
+            SharedValue::Array(arr) => {
+                7u8.hash(state);
+                arr.hash(state);
+            }
+            SharedValue::Object(o) => {
+                8u8.hash(state);
+                o.hash(state);
+            }
+            SharedValue::Map(pairs) => {
+                // Pairs have no order to sort by, so add up their hashes.
+                9u8.hash(state);
+                let sum = pairs.iter().fold(0u64, |sum, pair| {
+                    let mut h = DefaultHasher::new();
+                    pair.hash(&mut h);
+                    sum.wrapping_add(h.finish())
+                });
+                pairs.len().hash(state);
+                sum.hash(state);
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a Value> for SharedValue {
+    fn from(v: &'a Value) -> SharedValue {
+        match v {
+            Value::Null => SharedValue::Null,
+            Value::Bool(b) => SharedValue::Bool(*b),
+            Value::Int(n) => SharedValue::Int(*n),
+            Value::UInt(n) => SharedValue::UInt(*n),
+            Value::F32(n) => SharedValue::F32(*n),
+            Value::F64(n) => SharedValue::F64(*n),
+            Value::String(s) => SharedValue::String(Arc::from(s.as_str())),
+            Value::Binary(b) => SharedValue::Binary(Arc::from(b.as_slice())),
+            Value::Ext { type_id, data } => SharedValue::Ext {
+                type_id: *type_id,
+                data: Arc::from(data.as_slice()),
+            },
+            Value::Timestamp { secs, nanos } => SharedValue::Timestamp {
+                secs: *secs,
+                nanos: *nanos,
+            },
+            Value::Array(arr) => SharedValue::Array(arr.iter().map(SharedValue::from).collect()),
+            Value::Object(o) => SharedValue::Object(Arc::new(SharedObject::from(o))),
+            Value::Map(pairs) => SharedValue::Map(
+                pairs
+                    .iter()
+                    .map(|(k, v)| (SharedValue::from(k), SharedValue::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<Value> for SharedValue {
+    fn from(v: Value) -> SharedValue {
+        SharedValue::from(&v)
+    }
+}
+
+impl From<SharedValue> for Value {
+    fn from(v: SharedValue) -> Value {
+        v.to_value()
+    }
+}
+
+impl<'a> From<&'a SharedValue> for Value {
+    fn from(v: &'a SharedValue) -> Value {
+        v.to_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_round_trip() {
+        let v = value!({
+            "name": "a",
+            "tags": ["x", 1, null],
+            "nested": {"b": true, "f": 0.5}
+        });
+        let mut v = v;
+        if let Value::Object(o) = &mut v {
+            o.insert("bin".to_string(), Value::Binary(vec![1, 2]));
+            o.insert(
+                "map".to_string(),
+                Value::Map(vec![(Value::Int(1), Value::String("one".to_string()))]),
+            );
+        }
+
+        let shared = SharedValue::from(&v);
+        assert_eq!(shared.kind(), ValueKind::Object);
+        assert_eq!(shared.get("name").and_then(SharedValue::as_str), Some("a"));
+        assert_eq!(shared.pointer("/tags/1").and_then(|n| n.as_i64()), Some(1));
+        assert_eq!(
+            shared.pointer("/nested/f").and_then(|n| n.as_f64()),
+            Some(0.5)
+        );
+        assert_eq!(
+            shared.get("bin").and_then(|b| b.as_bytes()),
+            Some(&[1u8, 2][..])
+        );
+        assert!(shared.pointer("/tags/2").unwrap().is_null());
+        assert_eq!(shared.pointer("/tags/3"), None);
+        let keys: Vec<&str> = shared.as_object().unwrap().keys().map(|k| &**k).collect();
+        assert_eq!(keys, vec!["name", "tags", "nested", "bin", "map"]);
+
+        assert_eq!(shared.to_value(), v);
+        assert_eq!(Value::from(shared), v);
+    }
+
+    #[test]
+    fn test_eq_follows_value() {
+        fn hash_of(v: &SharedValue) -> u64 {
+            let mut h = DefaultHasher::new();
+            v.hash(&mut h);
+            h.finish()
+        }
+        let pairs = |a: &[(Value, Value)]| SharedValue::from(Value::Map(a.to_vec()));
+        let cases = vec![
+            (Value::Int(1), Value::UInt(1)),
+            (Value::F64(f64::NAN), Value::F32(f32::NAN)),
+            (Value::F64(0.0), Value::F64(-0.0)),
+            (Value::F32(1.5), Value::F64(1.5)),
+            (value!({"a": 1, "b": [2]}), value!({"b": [2], "a": 1})),
+        ];
+        for (a, b) in cases {
+            assert_eq!(a, b);
+            let (a, b) = (SharedValue::from(a), SharedValue::from(b));
+            assert_eq!(a, b);
+            assert_eq!(hash_of(&a), hash_of(&b));
+        }
+
+        let one = (Value::Int(1), Value::Null);
+        let two = (Value::UInt(2), Value::Bool(true));
+        assert_eq!(
+            pairs(&[one.clone(), two.clone()]),
+            pairs(&[two.clone(), one.clone()])
+        );
+        assert_eq!(
+            hash_of(&pairs(&[one.clone(), two.clone()])),
+            hash_of(&pairs(&[two.clone(), one.clone()]))
+        );
+        assert_ne!(pairs(&[one.clone(), one.clone()]), pairs(&[one, two]));
+
+        assert_ne!(SharedValue::Int(1), SharedValue::F64(1.0));
+        assert_ne!(SharedValue::Int(-1), SharedValue::UInt(u64::MAX));
+        assert_ne!(SharedValue::Null, SharedValue::Bool(false));
+    }
+
+    #[test]
+    fn test_clone_shares() {
+        assert_send_sync::<SharedValue>();
+
+        let shared = SharedValue::from(value!({"items": [1, 2, 3]}));
+        let copy = shared.clone();
+        match (&shared, &copy) {
+            (SharedValue::Object(a), SharedValue::Object(b)) => assert!(Arc::ptr_eq(a, b)),
+            _ => panic!("expected objects"),
+        }
+        assert_eq!(shared, copy);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let v = shared.clone();
+                thread::spawn(move || v.pointer("/items/2").and_then(|n| n.as_u64()))
+            })
+            .collect();
+        for h in handles {
+            assert_eq!(h.join().unwrap(), Some(3));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+// split_time returns the seconds relative to UNIX EPOCH and the non-negative
+// nanoseconds part, the same layout as the timestamp extension.
+pub fn split_time(t: SystemTime) -> (i64, u32) {
+    match t.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(n) => (n.as_secs() as i64, n.subsec_nanos()),
+        Err(e) => {
+            let n = e.duration();
+            if n.subsec_nanos() == 0 {
+                (-(n.as_secs() as i64), 0)
+            } else {
+                (-(n.as_secs() as i64) - 1, 1_000_000_000 - n.subsec_nanos())
+            }
+        }
+    }
+}
+
+pub fn join_time(secs: i64, nanos: u32) -> SystemTime {
+    if secs >= 0 {
+        SystemTime::UNIX_EPOCH + Duration::new(secs as u64, nanos)
+    } else {
+        SystemTime::UNIX_EPOCH - Duration::new(secs.unsigned_abs(), 0) + Duration::new(0, nanos)
+    }
+}
+
+// format_rfc3339 renders a timestamp as UTC, e.g. 2020-09-13T12:26:40.5Z.
+pub fn format_rfc3339(secs: i64, nanos: u32) -> String {
+    let mut result = String::new();
+    // Writing to a String cannot fail.
+    let _ = write_rfc3339(secs, nanos, &mut result);
+    result
+}
+
+// write_rfc3339 is format_rfc3339 without the intermediate String.
+pub fn write_rfc3339<W: fmt::Write + ?Sized>(secs: i64, nanos: u32, out: &mut W) -> fmt::Result {
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    write!(
+        out,
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )?;
+    if nanos != 0 {
+        let (mut frac, mut width) = (nanos, 9);
+        while frac % 10 == 0 {
+            frac /= 10;
+            width -= 1;
+        }
+        write!(out, ".{:0width$}", frac, width = width)?;
+    }
+    out.write_char('Z')
+}
+
+// civil_from_days converts days since 1970-01-01 to a proleptic Gregorian
+// (year, month, day), see http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_join_time() {
+        let t = SystemTime::UNIX_EPOCH - Duration::new(1, 1);
+        assert_eq!(split_time(t), (-2, 999_999_999));
+        assert_eq!(join_time(-2, 999_999_999), t);
+
+        let t = SystemTime::UNIX_EPOCH + Duration::new(1_600_000_000, 123);
+        assert_eq!(split_time(t), (1_600_000_000, 123));
+        assert_eq!(join_time(1_600_000_000, 123), t);
+    }
+
+    #[test]
+    fn test_format_rfc3339() {
+        assert_eq!(format_rfc3339(0, 0), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_rfc3339(1_600_000_000, 500_000_000),
+            "2020-09-13T12:26:40.5Z"
+        );
+        assert_eq!(format_rfc3339(-1, 0), "1969-12-31T23:59:59Z");
+        assert_eq!(format_rfc3339(951_782_400, 0), "2000-02-29T00:00:00Z");
+    }
+}
@@ -1,35 +1,54 @@
-use std::collections::HashMap;
+use crate::object_map::ObjectMap;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum Value {
+    #[default]
     Null,
     Bool(bool),
-    Number(String),
+    Int(i64),
+    UInt(u64),
+    F32(f32),
+    F64(f64),
     String(String),
+    Binary(Vec<u8>),
+    Ext {
+        type_id: i8,
+        data: Vec<u8>,
+    },
+    Timestamp {
+        secs: i64,
+        nanos: u32,
+    },
     Array(Vec<Value>),
-    Object(HashMap<String, Value>),
+    Object(ObjectMap),
+    Map(Vec<(Value, Value)>),
 }
 
-impl fmt::Display for Value {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}\n", self.format_value(self, 0))
-    }
-}
-
-impl Default for Value {
-    fn default() -> Self {
-        Value::Null
-    }
-}
+/// Wrapper that converts to and from `Value::Binary` instead of an array of
+/// integers, which is what a plain `Vec<u8>` turns into.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Bytes(pub Vec<u8>);
 
 impl Clone for Value {
     fn clone(&self) -> Self {
         match self {
             Value::Null => Value::Null,
             Value::Bool(b) => Value::Bool(*b),
-            Value::Number(n) => Value::Number(n.clone()),
+            Value::Int(n) => Value::Int(*n),
+            Value::UInt(n) => Value::UInt(*n),
+            Value::F32(n) => Value::F32(*n),
+            Value::F64(n) => Value::F64(*n),
             Value::String(s) => Value::String(s.clone()),
+            Value::Binary(b) => Value::Binary(b.clone()),
+            Value::Ext { type_id, data } => Value::Ext {
+                type_id: *type_id,
+                data: data.clone(),
+            },
+            Value::Timestamp { secs, nanos } => Value::Timestamp {
+                secs: *secs,
+                nanos: *nanos,
+            },
             Value::Array(arr) => {
                 let mut result: Vec<Value> = Vec::new();
                 for x in arr {
@@ -37,121 +56,268 @@ impl Clone for Value {
                 }
                 Value::Array(result)
             }
-            Value::Object(hm) => {
-                let mut result: HashMap<String, Value> = HashMap::new();
-                for key in hm.keys() {
-                    let value = hm.get(key).unwrap();
-                    result.insert(key.clone(), value.clone());
-                }
-                Value::Object(result)
-            }
+            Value::Object(o) => Value::Object(o.clone()),
+            Value::Map(pairs) => Value::Map(pairs.clone()),
         }
     }
 }
 
+/// The variant of a `Value`, without its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    Null,
+    Bool,
+    Int,
+    UInt,
+    F32,
+    F64,
+    String,
+    Binary,
+    Ext,
+    Timestamp,
+    Array,
+    Object,
+    Map,
+}
+
+impl ValueKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueKind::Null => "Null",
+            ValueKind::Bool => "Bool",
+            ValueKind::Int => "Int",
+            ValueKind::UInt => "UInt",
+            ValueKind::F32 => "F32",
+            ValueKind::F64 => "F64",
+            ValueKind::String => "String",
+            ValueKind::Binary => "Binary",
+            ValueKind::Ext => "Ext",
+            ValueKind::Timestamp => "Timestamp",
+            ValueKind::Array => "Array",
+            ValueKind::Object => "Object",
+            ValueKind::Map => "Map",
+        }
+    }
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 impl Value {
-    pub fn get_type(&self) -> String {
+    pub fn kind(&self) -> ValueKind {
         match self {
-            Value::Null => "Null".to_string(),
-            Value::Bool(_) => "Bool".to_string(),
-            Value::Number(_) => "Number".to_string(),
-            Value::String(_) => "String".to_string(),
-            Value::Array(_) => "Array".to_string(),
-            Value::Object(_) => "Object".to_string(),
+            Value::Null => ValueKind::Null,
+            Value::Bool(_) => ValueKind::Bool,
+            Value::Int(_) => ValueKind::Int,
+            Value::UInt(_) => ValueKind::UInt,
+            Value::F32(_) => ValueKind::F32,
+            Value::F64(_) => ValueKind::F64,
+            Value::String(_) => ValueKind::String,
+            Value::Binary(_) => ValueKind::Binary,
+            Value::Ext { .. } => ValueKind::Ext,
+            Value::Timestamp { .. } => ValueKind::Timestamp,
+            Value::Array(_) => ValueKind::Array,
+            Value::Object(_) => ValueKind::Object,
+            Value::Map(_) => ValueKind::Map,
         }
     }
 
+    #[deprecated(note = "use `as_str`, which borrows and does not panic")]
     pub fn get_string(&self) -> std::string::String {
         match self {
             Value::String(s) => s.clone(),
             _ => panic!("value is not string"),
         }
     }
-}
 
-impl Value {
-    fn format_value(&self, input: &Value, level: i32) -> String {
-        match input {
-            Value::Null => self.format_null(),
-            Value::Bool(b) => self.format_bool(*b),
-            Value::Number(n) => self.format_number(n.to_string()),
-            Value::String(s) => self.format_string(s.to_string()),
-            Value::Array(a) => self.format_array(a, level),
-            Value::Object(o) => self.format_object(o, level),
-        }
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
     }
 
-    fn format_null(&self) -> String {
-        "null".to_string()
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Value::Bool(_))
     }
 
-    fn format_bool(&self, input: bool) -> String {
-        if input {
-            return "true".to_string();
-        }
-        "false".to_string()
+    /// Whether `as_i64` returns a value.
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
     }
 
-    fn format_number(&self, input: String) -> String {
-        input
+    /// Whether `as_u64` returns a value.
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
     }
 
-    fn format_string(&self, input: String) -> String {
-        "\"".to_string() + &input + "\""
+    /// Whether this is an `F32` or an `F64`.
+    pub fn is_f64(&self) -> bool {
+        matches!(self, Value::F32(_) | Value::F64(_))
     }
 
-    fn format_array(&self, input: &Vec<Value>, level: i32) -> String {
-        let mut result = "[\n".to_string();
+    pub fn is_number(&self) -> bool {
+        matches!(
+            self,
+            Value::Int(_) | Value::UInt(_) | Value::F32(_) | Value::F64(_)
+        )
+    }
 
-        let mut idx = 0;
-        let length = input.len();
-        for item in input.iter() {
-            result += " ".repeat(((level + 1) * 4) as usize).as_str();
-            result += self.format_value(item, level + 1).as_str();
+    pub fn is_string(&self) -> bool {
+        matches!(self, Value::String(_))
+    }
 
-            if idx == length - 1 {
-                result += "\n";
-            } else {
-                result += ",\n";
-            }
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Value::Binary(_))
+    }
+
+    pub fn is_ext(&self) -> bool {
+        matches!(self, Value::Ext { .. })
+    }
+
+    pub fn is_timestamp(&self) -> bool {
+        matches!(self, Value::Timestamp { .. })
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, Value::Array(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, Value::Object(_))
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(self, Value::Map(_))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
 
-            idx += 1;
+    /// The integer, if this is an `Int` or a `UInt` that fits in `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            Value::UInt(n) if *n <= i64::MAX as u64 => Some(*n as i64),
+            _ => None,
         }
+    }
 
-        result += " ".repeat((level * 4) as usize).as_str();
-        result += "]";
-        result
+    /// The integer, if this is a `UInt` or a non-negative `Int`.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::UInt(n) => Some(*n),
+            Value::Int(n) if *n >= 0 => Some(*n as u64),
+            _ => None,
+        }
     }
 
-    fn format_object(&self, input: &HashMap<String, Value>, level: i32) -> String {
-        let mut result = "{\n".to_string();
+    /// Any number as `f64`. Integers beyond 2^53 lose precision.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::UInt(n) => Some(*n as f64),
+            Value::F32(f) => Some(*f as f64),
+            Value::F64(f) => Some(*f),
+            _ => None,
+        }
+    }
 
-        let mut idx = 0;
-        let length = input.len();
-        for (k, v) in input {
-            result += " ".repeat(((level + 1) * 4) as usize).as_str();
-            result = result + "\"" + &k + "\": ";
-            result += self.format_value(&v, level + 1).as_str();
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
 
-            if idx == length - 1 {
-                result += "\n";
-            } else {
-                result += ",\n";
-            }
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Binary(b) => Some(b),
+            _ => None,
+        }
+    }
 
-            idx += 1;
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(arr) => Some(arr),
+            _ => None,
         }
+    }
 
-        result += " ".repeat((level * 4) as usize).as_str();
-        result += "}";
-        result
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&ObjectMap> {
+        match self {
+            Value::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut ObjectMap> {
+        match self {
+            Value::Object(o) => Some(o),
+            _ => None,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn test_accessors() {
+        let mut v =
+            value!({"n": 1, "big": (u64::MAX), "neg": (-1), "f": 0.5, "s": "x", "a": [true]});
+        assert_eq!(v["n"].as_i64(), Some(1));
+        assert_eq!(v["n"].as_u64(), Some(1));
+        assert_eq!(v["n"].as_f64(), Some(1.0));
+        assert_eq!(v["big"].as_i64(), None);
+        assert_eq!(v["big"].as_u64(), Some(u64::MAX));
+        assert_eq!(v["neg"].as_u64(), None);
+        assert_eq!(v["f"].as_i64(), None);
+        assert_eq!(v["f"].as_f64(), Some(0.5));
+        assert!(v["f"].is_f64() && v["f"].is_number() && !v["n"].is_f64());
+        assert_eq!(v["s"].as_str(), Some("x"));
+        assert_eq!(v["s"].as_bytes(), None);
+        assert_eq!(v["a"][0].as_bool(), Some(true));
+        assert_eq!(v["a"].as_array().map(Vec::len), Some(1));
+        assert!(v["missing"].is_null());
+        assert!(v.as_array().is_none());
+
+        v.get_mut("a")
+            .unwrap()
+            .as_array_mut()
+            .unwrap()
+            .push(Value::Null);
+        v.as_object_mut().unwrap().remove("big");
+        assert_eq!(v["a"].as_array().unwrap().len(), 2);
+        assert!(!v.as_object().unwrap().contains_key("big"));
+        assert_eq!(Value::Binary(vec![1]).as_bytes(), Some(&[1u8][..]));
+    }
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(Value::Null.kind(), ValueKind::Null);
+        assert_eq!(Value::UInt(1).kind(), ValueKind::UInt);
+        assert_eq!(value!({}).kind(), ValueKind::Object);
+        assert_eq!(
+            Value::Timestamp { secs: 0, nanos: 0 }.kind().to_string(),
+            "Timestamp"
+        );
+    }
 }
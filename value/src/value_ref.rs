@@ -0,0 +1,181 @@
+//! A borrowed counterpart of `Value`.
+//!
+//! Strings, binaries and extension payloads point into the buffer the tree
+//! was decoded from, so inspecting a few fields of a message does not copy
+//! the rest of it. `to_value()` turns the tree into a `Value` when it has to
+//! outlive the buffer.
+
+use crate::object_map::ObjectMap;
+use crate::pointer::{parse_array_index, parse_pointer};
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRef<'a> {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    F32(f32),
+    F64(f64),
+    String(&'a str),
+    Binary(&'a [u8]),
+    Ext {
+        type_id: i8,
+        data: &'a [u8],
+    },
+    Timestamp {
+        secs: i64,
+        nanos: u32,
+    },
+    Array(Vec<ValueRef<'a>>),
+    /// Members in the order they were decoded.
+    Object(Vec<(&'a str, ValueRef<'a>)>),
+    Map(Vec<(ValueRef<'a>, ValueRef<'a>)>),
+}
+
+impl<'a> ValueRef<'a> {
+    /// Member `key` of an object. A key that appears more than once resolves
+    /// to its last occurrence, as it does in `Value`.
+    pub fn get(&self, key: &str) -> Option<&ValueRef<'a>> {
+        match self {
+            ValueRef::Object(members) => members
+                .iter()
+                .rev()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Looks up a JSON Pointer (RFC 6901), like `Value::pointer`. A token
+    /// reaches the first `Map` entry whose key is that string.
+    pub fn pointer(&self, pointer: &str) -> Option<&ValueRef<'a>> {
+        let mut target = self;
+        for token in parse_pointer(pointer)? {
+            target = match target {
+                ValueRef::Object(_) => target.get(&token)?,
+                ValueRef::Array(arr) => arr.get(parse_array_index(&token)?)?,
+                ValueRef::Map(pairs) => pairs
+                    .iter()
+                    .find(|(k, _)| *k == ValueRef::String(&token))
+                    .map(|(_, v)| v)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            ValueRef::Null => Value::Null,
+            ValueRef::Bool(b) => Value::Bool(*b),
+            ValueRef::Int(n) => Value::Int(*n),
+            ValueRef::UInt(n) => Value::UInt(*n),
+            ValueRef::F32(n) => Value::F32(*n),
+            ValueRef::F64(n) => Value::F64(*n),
+            ValueRef::String(s) => Value::String(s.to_string()),
+            ValueRef::Binary(b) => Value::Binary(b.to_vec()),
+            ValueRef::Ext { type_id, data } => Value::Ext {
+                type_id: *type_id,
+                data: data.to_vec(),
+            },
+            ValueRef::Timestamp { secs, nanos } => Value::Timestamp {
+                secs: *secs,
+                nanos: *nanos,
+            },
+            ValueRef::Array(arr) => Value::Array(arr.iter().map(|v| v.to_value()).collect()),
+            ValueRef::Object(members) => {
+                let mut result = ObjectMap::with_capacity(members.len());
+                for (k, v) in members {
+                    result.insert(k.to_string(), v.to_value());
+                }
+                Value::Object(result)
+            }
+            ValueRef::Map(pairs) => Value::Map(
+                pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_value(), v.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl<'a> From<ValueRef<'a>> for Value {
+    fn from(v: ValueRef<'a>) -> Value {
+        v.to_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_and_pointer() {
+        let v = ValueRef::Object(vec![
+            ("a", ValueRef::Int(1)),
+            (
+                "b",
+                ValueRef::Array(vec![ValueRef::Null, ValueRef::String("x")]),
+            ),
+            ("a", ValueRef::Int(2)),
+        ]);
+        assert_eq!(v.get("a"), Some(&ValueRef::Int(2)));
+        assert_eq!(v.get("c"), None);
+        assert_eq!(v.pointer("/b/1"), Some(&ValueRef::String("x")));
+        assert_eq!(v.pointer(""), Some(&v));
+        assert_eq!(v.pointer("/b/2"), None);
+        assert_eq!(v.pointer("/a/0"), None);
+
+        let m = ValueRef::Map(vec![
+            (ValueRef::Int(1), ValueRef::Null),
+            (
+                ValueRef::String("k"),
+                ValueRef::Array(vec![ValueRef::Int(3)]),
+            ),
+            (ValueRef::String("k"), ValueRef::Int(4)),
+        ]);
+        assert_eq!(m.pointer("/k/0"), Some(&ValueRef::Int(3)));
+        assert_eq!(m.pointer("/1"), None);
+        assert_eq!(m.to_value().pointer("/k/0"), Some(&Value::Int(3)));
+    }
+
+    #[test]
+    fn test_to_value() {
+        let data = [1u8, 2, 3];
+        let v = ValueRef::Object(vec![
+            ("s", ValueRef::String("x")),
+            ("bin", ValueRef::Binary(&data)),
+            (
+                "ext",
+                ValueRef::Ext {
+                    type_id: 3,
+                    data: &data[..1],
+                },
+            ),
+            (
+                "map",
+                ValueRef::Map(vec![(ValueRef::UInt(1), ValueRef::Bool(true))]),
+            ),
+            ("arr", ValueRef::Array(vec![ValueRef::F64(0.5)])),
+        ]);
+
+        let owned = v.to_value();
+        assert_eq!(owned["s"], Value::String("x".to_string()));
+        assert_eq!(owned["bin"], Value::Binary(vec![1, 2, 3]));
+        assert_eq!(
+            owned["ext"],
+            Value::Ext {
+                type_id: 3,
+                data: vec![1]
+            }
+        );
+        assert_eq!(
+            owned["map"],
+            Value::Map(vec![(Value::Int(1), Value::Bool(true))])
+        );
+        assert_eq!(owned["arr"][0], Value::F64(0.5));
+        assert_eq!(Value::from(v), owned);
+    }
+}
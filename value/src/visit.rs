@@ -0,0 +1,291 @@
+//! Walking and rewriting `Value` trees.
+//!
+//! Every callback gets the path from the root to the current node. Object
+//! members are reached by `Key`, array elements by `Index` and map values by
+//! `MapKey`; map keys themselves are not visited.
+
+use crate::format::Formatter;
+use crate::object_map::ObjectMap;
+use crate::pointer::escape_pointer_token;
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathSegment<'a> {
+    Key(&'a str),
+    Index(usize),
+    MapKey(&'a Value),
+}
+
+/// Renders `path` as a JSON Pointer. Map keys that are not strings are
+/// written as compact JSON.
+pub fn path_to_pointer(path: &[PathSegment<'_>]) -> String {
+    let mut result = String::new();
+    for segment in path {
+        result += "/";
+        match segment {
+            PathSegment::Key(k) => result += &escape_pointer_token(k),
+            PathSegment::Index(i) => result += &i.to_string(),
+            PathSegment::MapKey(Value::String(k)) => result += &escape_pointer_token(k),
+            PathSegment::MapKey(k) => {
+                result += &escape_pointer_token(&Formatter::compact().format(k))
+            }
+        }
+    }
+    result
+}
+
+/// What the walk does after `Visitor::enter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    Continue,
+    /// Do not descend into this node; `leave` is still called for it.
+    SkipChildren,
+    /// End the walk without calling any further callbacks.
+    Stop,
+}
+
+/// Callbacks for `Value::walk`. `enter` runs before a node's children
+/// (pre-order), `leave` after them (post-order).
+pub trait Visitor {
+    fn enter(&mut self, _path: &[PathSegment<'_>], _v: &Value) -> Walk {
+        Walk::Continue
+    }
+
+    fn leave(&mut self, _path: &[PathSegment<'_>], _v: &Value) {}
+}
+
+impl Value {
+    pub fn walk<V: Visitor>(&self, visitor: &mut V) {
+        let mut path = Vec::new();
+        walk_inner(self, &mut path, visitor);
+    }
+
+    /// Calls `f` on every node, parents before children. Children are taken
+    /// from the node as `f` left it, so a replaced node is walked in its new
+    /// shape.
+    pub fn walk_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&[PathSegment<'_>], &mut Value),
+    {
+        let mut path = Vec::new();
+        walk_mut_inner(self, &mut path, &mut f);
+    }
+
+    /// Builds a new tree bottom-up: each node is rebuilt from its
+    /// transformed children and then passed to `f`, whose result takes its
+    /// place.
+    pub fn transform<F>(&self, mut f: F) -> Value
+    where
+        F: FnMut(&[PathSegment<'_>], Value) -> Value,
+    {
+        let mut path = Vec::new();
+        transform_inner(self, &mut path, &mut f)
+    }
+}
+
+// Returns false once the visitor asked to stop.
+fn walk_inner<'a, V: Visitor>(
+    v: &'a Value,
+    path: &mut Vec<PathSegment<'a>>,
+    visitor: &mut V,
+) -> bool {
+    match visitor.enter(path, v) {
+        Walk::Stop => return false,
+        Walk::SkipChildren => {}
+        Walk::Continue => {
+            let completed = match v {
+                Value::Array(arr) => arr.iter().enumerate().all(|(i, child)| {
+                    path.push(PathSegment::Index(i));
+                    let completed = walk_inner(child, path, visitor);
+                    path.pop();
+                    completed
+                }),
+                Value::Object(o) => o.iter().all(|(k, child)| {
+                    path.push(PathSegment::Key(k));
+                    let completed = walk_inner(child, path, visitor);
+                    path.pop();
+                    completed
+                }),
+                Value::Map(pairs) => pairs.iter().all(|(k, child)| {
+                    path.push(PathSegment::MapKey(k));
+                    let completed = walk_inner(child, path, visitor);
+                    path.pop();
+                    completed
+                }),
+                _ => true,
+            };
+            if !completed {
+                return false;
+            }
+        }
+    }
+    visitor.leave(path, v);
+    true
+}
+
+fn walk_mut_inner<'a, F>(v: &'a mut Value, path: &mut Vec<PathSegment<'a>>, f: &mut F)
+where
+    F: FnMut(&[PathSegment<'_>], &mut Value),
+{
+    f(path, v);
+    match v {
+        Value::Array(arr) => {
+            for (i, child) in arr.iter_mut().enumerate() {
+                path.push(PathSegment::Index(i));
+                walk_mut_inner(child, path, f);
+                path.pop();
+            }
+        }
+        Value::Object(o) => {
+            for (k, child) in o.iter_mut() {
+                path.push(PathSegment::Key(k));
+                walk_mut_inner(child, path, f);
+                path.pop();
+            }
+        }
+        Value::Map(pairs) => {
+            for (k, child) in pairs.iter_mut() {
+                path.push(PathSegment::MapKey(k));
+                walk_mut_inner(child, path, f);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn transform_inner<'a, F>(v: &'a Value, path: &mut Vec<PathSegment<'a>>, f: &mut F) -> Value
+where
+    F: FnMut(&[PathSegment<'_>], Value) -> Value,
+{
+    let rebuilt = match v {
+        Value::Array(arr) => {
+            let mut result = Vec::with_capacity(arr.len());
+            for (i, child) in arr.iter().enumerate() {
+                path.push(PathSegment::Index(i));
+                result.push(transform_inner(child, path, f));
+                path.pop();
+            }
+            Value::Array(result)
+        }
+        Value::Object(o) => {
+            let mut result = ObjectMap::with_capacity(o.len());
+            for (k, child) in o {
+                path.push(PathSegment::Key(k));
+                result.insert(k.clone(), transform_inner(child, path, f));
+                path.pop();
+            }
+            Value::Object(result)
+        }
+        Value::Map(pairs) => {
+            let mut result = Vec::with_capacity(pairs.len());
+            for (k, child) in pairs {
+                path.push(PathSegment::MapKey(k));
+                result.push((k.clone(), transform_inner(child, path, f)));
+                path.pop();
+            }
+            Value::Map(result)
+        }
+        other => other.clone(),
+    };
+    f(path, rebuilt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        stop_at: Option<String>,
+    }
+
+    impl Visitor for Recorder {
+        fn enter(&mut self, path: &[PathSegment<'_>], v: &Value) -> Walk {
+            let pointer = path_to_pointer(path);
+            self.events.push(format!("enter {}", pointer));
+            if self.stop_at.as_deref() == Some(pointer.as_str()) {
+                return Walk::Stop;
+            }
+            if let Value::Object(o) = v {
+                if o.contains_key("skip") {
+                    return Walk::SkipChildren;
+                }
+            }
+            Walk::Continue
+        }
+
+        fn leave(&mut self, path: &[PathSegment<'_>], _v: &Value) {
+            self.events.push(format!("leave {}", path_to_pointer(path)));
+        }
+    }
+
+    #[test]
+    fn test_walk_order() {
+        let v = value!({"a": [1, {"skip": 1}], "b/c": 2});
+        let mut r = Recorder::default();
+        v.walk(&mut r);
+        assert_eq!(
+            r.events,
+            vec![
+                "enter ",
+                "enter /a",
+                "enter /a/0",
+                "leave /a/0",
+                "enter /a/1",
+                "leave /a/1",
+                "leave /a",
+                "enter /b~1c",
+                "leave /b~1c",
+                "leave ",
+            ]
+        );
+
+        let mut r = Recorder {
+            stop_at: Some("/a/0".to_string()),
+            ..Recorder::default()
+        };
+        v.walk(&mut r);
+        assert_eq!(r.events, vec!["enter ", "enter /a", "enter /a/0"]);
+    }
+
+    #[test]
+    fn test_walk_map_keys() {
+        let v = Value::Map(vec![(Value::Int(1), value!(["x"]))]);
+        let mut r = Recorder::default();
+        v.walk(&mut r);
+        assert_eq!(r.events[2], "enter /1/0");
+    }
+
+    #[test]
+    fn test_walk_mut() {
+        let mut v = value!({"name": "a", "tags": ["b", 1], "n": null});
+        let mut paths = Vec::new();
+        v.walk_mut(|path, node| {
+            paths.push(path_to_pointer(path));
+            match node {
+                Value::String(s) => s.make_ascii_uppercase(),
+                Value::Null => *node = value!(["c"]),
+                _ => {}
+            }
+        });
+        assert_eq!(v, value!({"name": "A", "tags": ["B", 1], "n": ["C"]}));
+        assert_eq!(
+            paths,
+            vec!["", "/name", "/tags", "/tags/0", "/tags/1", "/n", "/n/0"]
+        );
+    }
+
+    #[test]
+    fn test_transform() {
+        let v = value!({"a": [1, 2], "b": {"c": 3}});
+        let doubled = v.transform(|path, node| match node {
+            Value::Int(n) if path.len() == 2 => Value::Int(n * 2),
+            Value::Object(o) if !path.is_empty() => Value::Int(o.len() as i64),
+            node => node,
+        });
+        assert_eq!(doubled, value!({"a": [2, 4], "b": 1}));
+        assert_eq!(v, value!({"a": [1, 2], "b": {"c": 3}}));
+    }
+}
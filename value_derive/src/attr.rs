@@ -0,0 +1,26 @@
+use syn::{Field, Ident, Lit, Meta, NestedMeta};
+
+// field_key returns the object key a field is stored under: the field name,
+// or the string given by `#[value(rename = "...")]`.
+pub fn field_key(field: &Field, ident: &Ident) -> String {
+    let mut key = ident.to_string();
+    for attr in field.attrs.iter() {
+        let list = match attr.interpret_meta() {
+            Some(Meta::List(list)) => list,
+            _ => continue,
+        };
+        if list.ident != "value" {
+            continue;
+        }
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.ident == "rename" => match nv.lit {
+                    Lit::Str(ref s) => key = s.value(),
+                    _ => panic!("rename for field {} must be a string", ident),
+                },
+                _ => panic!("unsupported value attribute on field {}", ident),
+            }
+        }
+    }
+    key
+}
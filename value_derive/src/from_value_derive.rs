@@ -1,4 +1,5 @@
 // use proc_macro2::TokenStream;
+use crate::attr::field_key;
 use quote::quote;
 // use syn::spanned::Spanned;
 use syn::{
@@ -16,17 +17,21 @@ use syn::{
 pub fn from_value_derive_inner(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
-    let idents: Vec<Ident> = match input.data {
+    let (idents, keys): (Vec<Ident>, Vec<String>) = match input.data {
         Data::Struct(ref data) => match data.fields {
             Fields::Named(ref fields) => {
                 let mut idents: Vec<Ident> = Vec::new();
-                for ref field in fields.named.iter() {
+                let mut keys: Vec<String> = Vec::new();
+                for field in fields.named.iter() {
                     match &field.ident {
-                        &Some(ref ident) => idents.push(ident.clone()),
+                        Some(ident) => {
+                            idents.push(ident.clone());
+                            keys.push(field_key(field, ident));
+                        }
                         &None => panic!("Your struct is missing a field identity!"),
                     }
                 }
-                idents
+                (idents, keys)
             }
             Fields::Unnamed(_) => unimplemented!(),
             Fields::Unit => unimplemented!(),
@@ -34,11 +39,6 @@ pub fn from_value_derive_inner(input: proc_macro::TokenStream) -> proc_macro::To
         Data::Enum(_) | Data::Union(_) => unimplemented!(),
     };
 
-    let mut keys: Vec<String> = Vec::new();
-    for ident in idents.iter() {
-        keys.push(ident.to_string());
-    }
-
     let name = input.ident;
 
     let generics = add_trait_bounds_from_value(input.generics);
@@ -49,15 +49,14 @@ pub fn from_value_derive_inner(input: proc_macro::TokenStream) -> proc_macro::To
 
             fn from_value(&self) -> #name {
                 let mut settings = #name::default();
-                let mut hm: ::std::collections::HashMap<::std::string::String, value::value::Value> = self.from_value();
+                let obj = match self {
+                    value::value::Value::Object(obj) => obj,
+                    _ => panic!("invalid value for {}", stringify!(#name)),
+                };
 
                 #(
-                    match hm.entry(String::from(#keys)) {
-                        ::std::collections::hash_map::Entry::Occupied(ent) => {
-                            let v = ent.remove();
-                            settings.#idents = v.from_value();
-                        },
-                        ::std::collections::hash_map::Entry::Vacant(_) => (),
+                    if let Some(v) = obj.get(#keys) {
+                        settings.#idents = v.from_value();
                     }
                 )*
 
@@ -1,4 +1,5 @@
 // use proc_macro2::TokenStream;
+use crate::attr::field_key;
 use quote::quote;
 // use syn::spanned::Spanned;
 use syn::{
@@ -16,17 +17,21 @@ use syn::{
 pub fn into_value_derive_inner(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
-    let idents: Vec<Ident> = match input.data {
+    let (idents, keys): (Vec<Ident>, Vec<String>) = match input.data {
         Data::Struct(ref data) => match data.fields {
             Fields::Named(ref fields) => {
                 let mut idents: Vec<Ident> = Vec::new();
-                for ref field in fields.named.iter() {
+                let mut keys: Vec<String> = Vec::new();
+                for field in fields.named.iter() {
                     match &field.ident {
-                        &Some(ref ident) => idents.push(ident.clone()),
+                        Some(ident) => {
+                            idents.push(ident.clone());
+                            keys.push(field_key(field, ident));
+                        }
                         &None => panic!("Your struct is missing a field identity!"),
                     }
                 }
-                idents
+                (idents, keys)
             }
             Fields::Unnamed(_) => unimplemented!(),
             Fields::Unit => unimplemented!(),
@@ -34,30 +39,25 @@ pub fn into_value_derive_inner(input: proc_macro::TokenStream) -> proc_macro::To
         Data::Enum(_) | Data::Union(_) => unimplemented!(),
     };
 
-    let mut keys: Vec<String> = Vec::new();
-    for ident in idents.iter() {
-        keys.push(ident.to_string());
-    }
-
     let name = input.ident;
 
     let generics = add_trait_bounds_into_value(input.generics);
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let expanded = quote! {
-        impl #impl_generics IntoValue<#name> for #name #ty_generics #where_clause {
+        impl #impl_generics IntoValue for #name #ty_generics #where_clause {
 
             fn into_value(&self) -> value::value::Value {
-                let mut hm: ::std::collections::HashMap<::std::string::String, value::value::Value> = ::std::collections::HashMap::new();
+                let mut obj = value::object_map::ObjectMap::new();
 
                 #(
-                    hm.insert(
+                    obj.insert(
                         #keys.to_string(),
                         self.#idents.into_value(),
                         );
                 )*
 
-                hm.into_value()
+                value::value::Value::Object(obj)
             }
         }
     };
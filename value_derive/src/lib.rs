@@ -1,15 +1,20 @@
 #![recursion_limit = "1024"]
 extern crate proc_macro;
 
+mod attr;
 mod from_value_derive;
 mod into_value_derive;
 
-#[proc_macro_derive(FromValue)]
+/// Reads a struct from a `Value::Object`, one member per field. Members
+/// are looked up by field name unless the field has
+/// `#[value(rename = "key")]`; missing members leave the default.
+#[proc_macro_derive(FromValue, attributes(value))]
 pub fn from_value_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     from_value_derive::from_value_derive_inner(input)
 }
 
-#[proc_macro_derive(IntoValue)]
+/// Writes a struct as a `Value::Object`, with the same keys as `FromValue`.
+#[proc_macro_derive(IntoValue, attributes(value))]
 pub fn into_value_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     into_value_derive::into_value_derive_inner(input)
 }
@@ -0,0 +1,65 @@
+// Checks the derives against hand-written values.
+#[macro_use]
+extern crate value;
+
+use value::from_value::FromValue;
+use value::into_value::IntoValue;
+use value::value::Value;
+use value_derive::{FromValue, IntoValue};
+
+#[derive(Debug, Default, PartialEq, FromValue, IntoValue)]
+struct Renamed {
+    #[value(rename = "userName")]
+    user_name: String,
+    #[value(rename = "type")]
+    kind: i64,
+    plain: bool,
+}
+
+#[test]
+fn test_rename() {
+    let r = Renamed {
+        user_name: "a".to_string(),
+        kind: 2,
+        plain: true,
+    };
+    let v = r.into_value();
+    assert_eq!(v, value!({"userName": "a", "type": 2, "plain": true}));
+    let back: Renamed = v.from_value();
+    assert_eq!(back, r);
+
+    let partial: Renamed = value!({"user_name": "b", "type": 3}).from_value();
+    assert_eq!(
+        partial,
+        Renamed {
+            kind: 3,
+            ..Renamed::default()
+        }
+    );
+}
+
+#[derive(Debug, Default, PartialEq, FromValue, IntoValue)]
+struct Optional {
+    a: Option<i64>,
+    b: Option<String>,
+    c: Option<Vec<bool>>,
+}
+
+#[test]
+fn test_option_fields() {
+    let v = value!({"a": 1, "b": null, "c": [true]});
+    let o: Optional = v.from_value();
+    assert_eq!(
+        o,
+        Optional {
+            a: Some(1),
+            b: None,
+            c: Some(vec![true]),
+        }
+    );
+    assert_eq!(o.into_value(), v);
+
+    let missing: Optional = value!({}).from_value();
+    assert_eq!(missing, Optional::default());
+    assert_eq!(None::<i64>.into_value(), Value::Null);
+}
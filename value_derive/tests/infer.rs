@@ -0,0 +1,49 @@
+// Checks that the structs `Inference::to_rust` writes compile against the
+// derives, including when sample keys collide with the imported names.
+#[macro_use]
+extern crate value;
+
+use value::from_value::FromValue;
+use value::infer::Inference;
+use value::into_value::IntoValue;
+use value::value::Value;
+
+mod generated {
+    include!("infer/generated.rs");
+}
+
+fn sample() -> Value {
+    let mut v = value!({
+        "value": {"a": 1},
+        "bytes": {"b": "x"},
+        "string": {"c": true},
+        "vec": [{"d": 1.5}],
+        "option": {"e": "y"},
+        "systemTime": {"f": 2},
+        "fromValue": {"g": 3},
+        "intoValue": {"h": 4},
+        "any": null
+    });
+    if let Value::Object(o) = &mut v {
+        o.insert("at".to_string(), Value::Timestamp { secs: 1, nanos: 2 });
+        o.insert("raw".to_string(), Value::Binary(vec![1, 2]));
+    }
+    v
+}
+
+#[test]
+fn test_generated_is_current() {
+    let mut inference = Inference::new();
+    inference.add(&sample());
+    assert_eq!(
+        inference.to_rust("Value"),
+        include_str!("infer/generated.rs")
+    );
+}
+
+#[test]
+fn test_generated_roundtrip() {
+    let v = sample();
+    let parsed: generated::Value2 = v.from_value();
+    assert_eq!(parsed.into_value(), v);
+}
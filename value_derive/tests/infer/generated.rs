@@ -0,0 +1,63 @@
+use std::time::SystemTime;
+use value::from_value::FromValue;
+use value::into_value::IntoValue;
+use value::value::{Bytes, Value};
+use value_derive::{FromValue, IntoValue};
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct Value2 {
+    pub value: Value3,
+    pub bytes: Bytes2,
+    pub string: String2,
+    pub vec: Vec<VecItem>,
+    pub option: Option2,
+    #[value(rename = "systemTime")]
+    pub system_time: SystemTime2,
+    #[value(rename = "fromValue")]
+    pub from_value: FromValue2,
+    #[value(rename = "intoValue")]
+    pub into_value: IntoValue2,
+    pub any: Value,
+    pub at: Option<SystemTime>,
+    pub raw: Bytes,
+}
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct IntoValue2 {
+    pub h: i64,
+}
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct FromValue2 {
+    pub g: i64,
+}
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct SystemTime2 {
+    pub f: i64,
+}
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct Option2 {
+    pub e: String,
+}
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct VecItem {
+    pub d: f64,
+}
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct String2 {
+    pub c: bool,
+}
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct Bytes2 {
+    pub b: String,
+}
+
+#[derive(Debug, Clone, Default, FromValue, IntoValue)]
+pub struct Value3 {
+    pub a: i64,
+}